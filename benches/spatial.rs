@@ -0,0 +1,123 @@
+// compares the spatial grid against the linear scans it replaced
+// run with `cargo bench --bench spatial`
+#![allow(dead_code)]
+
+use bevy::prelude::*;
+use rand::prelude::*;
+use std::time::{Duration, Instant};
+
+#[path = "../src/spatial.rs"]
+mod spatial;
+
+use spatial::SpatialGrid;
+
+const QUERIES: usize = 1000;
+const LIGHTNING_RADIUS: f32 = 93.75;
+
+fn random_positions(rng: &mut StdRng, count: usize) -> Vec<(Entity, Vec2)> {
+  (0..count)
+    .map(|i| {
+      (
+        Entity::from_raw(i as u32),
+        Vec2::new(rng.gen_range(-600.0..600.0), rng.gen_range(-600.0..600.0)),
+      )
+    })
+    .collect()
+}
+
+fn linear_nearest(entities: &[(Entity, Vec2)], center: Vec2) -> Option<(Entity, Vec2)> {
+  entities.iter().copied().min_by(|(_, a), (_, b)| {
+    a.distance_squared(center)
+      .total_cmp(&b.distance_squared(center))
+  })
+}
+
+fn linear_radius(entities: &[(Entity, Vec2)], center: Vec2, radius: f32) -> usize {
+  entities
+    .iter()
+    .filter(|(_, position)| position.distance(center) < radius)
+    .count()
+}
+
+fn time<T>(mut f: impl FnMut() -> T) -> (Duration, T) {
+  let start = Instant::now();
+  let result = f();
+  (start.elapsed(), result)
+}
+
+fn per_query(duration: Duration) -> String {
+  format!("{:>8.2}us", duration.as_secs_f64() * 1e6 / QUERIES as f64)
+}
+
+fn main() {
+  let mut rng = StdRng::seed_from_u64(7);
+
+  println!(
+    "{:>8} {:>10} | {:>10} {:>10} | {:>10} {:>10}",
+    "enemies", "rebuild", "nearest", "linear", "radius", "linear"
+  );
+
+  for count in [100, 1_000, 5_000, 10_000, 50_000] {
+    let entities = random_positions(&mut rng, count);
+    let centers: Vec<Vec2> = random_positions(&mut rng, QUERIES)
+      .into_iter()
+      .map(|(_, position)| position)
+      .collect();
+
+    let mut grid = SpatialGrid::default();
+    let (rebuild, _) = time(|| {
+      grid.clear();
+      for (entity, position) in entities.iter() {
+        grid.insert(*entity, *position);
+      }
+    });
+
+    let (grid_nearest, grid_results) = time(|| {
+      centers
+        .iter()
+        .map(|center| grid.nearest(*center))
+        .collect::<Vec<_>>()
+    });
+    let (scan_nearest, scan_results) = time(|| {
+      centers
+        .iter()
+        .map(|center| linear_nearest(&entities, *center))
+        .collect::<Vec<_>>()
+    });
+
+    // both have to agree on the distance, ties may pick different entities
+    for ((grid, scan), center) in grid_results
+      .iter()
+      .zip(scan_results.iter())
+      .zip(centers.iter())
+    {
+      let grid = grid.unwrap().1.distance(*center);
+      let scan = scan.unwrap().1.distance(*center);
+      assert!((grid - scan).abs() < 1e-3, "nearest mismatch");
+    }
+
+    let (grid_radius, grid_hits) = time(|| {
+      centers
+        .iter()
+        .map(|center| grid.query_radius(*center, LIGHTNING_RADIUS).count())
+        .sum::<usize>()
+    });
+    let (scan_radius, scan_hits) = time(|| {
+      centers
+        .iter()
+        .map(|center| linear_radius(&entities, *center, LIGHTNING_RADIUS))
+        .sum::<usize>()
+    });
+    assert_eq!(grid_hits, scan_hits, "radius mismatch");
+
+    println!(
+      "{:>8} {:>8.2}ms | {} {} | {} {}",
+      count,
+      rebuild.as_secs_f64() * 1e3,
+      per_query(grid_nearest),
+      per_query(scan_nearest),
+      per_query(grid_radius),
+      per_query(scan_radius),
+    );
+  }
+}
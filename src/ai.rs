@@ -0,0 +1,371 @@
+use crate::components::*;
+use crate::enemy::{closest_player, Enemy, EnemyScaling};
+use crate::fixed::{GameRng, GameTime};
+use crate::navigation::Navigation;
+use crate::player::Player;
+use crate::spatial::SpatialIndex;
+use bevy::prelude::*;
+use bevy::utils::HashMap;
+use bevy_rapier2d::prelude::*;
+use rand::prelude::*;
+
+const WINDUP_COLOR: Color = Color::rgb(1.0, 0.3, 0.3);
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum AiState {
+  Idle,
+  Wander,
+  Chase,
+  // standing still and blinking before a charge, the direction is already locked in
+  Windup,
+  Charge,
+  Recover,
+  Flee,
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct ChargeParams {
+  pub cooldown: f32,
+  // players further away than this are not charged at
+  pub range: f32,
+  pub windup: f32,
+  pub duration: f32,
+  pub recover: f32,
+  pub speed: f32,
+}
+
+// steering weights blended into the velocity while roaming, so hordes spread out around players
+#[derive(Clone, Copy, Debug)]
+pub struct FlockingParams {
+  // only enemies closer than this are taken into account
+  pub radius: f32,
+  // push away from close neighbours
+  pub separation: f32,
+  // match the heading of neighbours
+  pub alignment: f32,
+  // pull towards the center of the group
+  pub cohesion: f32,
+}
+
+impl Default for FlockingParams {
+  fn default() -> Self {
+    FlockingParams {
+      radius: 40.0,
+      separation: 1.0,
+      alignment: 0.0,
+      cohesion: 0.0,
+    }
+  }
+}
+
+// everything the state machine needs to decide on transitions, set per enemy type when spawning
+#[derive(Clone, Copy, Debug)]
+pub struct AiParams {
+  // seconds spent standing still after spawning
+  pub idle_time: f32,
+  pub wander_speed: f32,
+  pub chase_speed: f32,
+  // players within this range are chased, f32::INFINITY always chases and 0.0 never does
+  pub sight_range: f32,
+  // ranged enemies hover at this distance instead of closing in
+  pub keep_distance: Option<f32>,
+  // run away from players while below this fraction of max health
+  pub flee_below: f32,
+  pub charge: Option<ChargeParams>,
+  pub flocking: Option<FlockingParams>,
+}
+
+impl Default for AiParams {
+  fn default() -> Self {
+    AiParams {
+      idle_time: 0.5,
+      wander_speed: 75.0,
+      chase_speed: 60.0,
+      sight_range: 0.0,
+      keep_distance: None,
+      flee_below: 0.0,
+      charge: None,
+      flocking: Some(FlockingParams::default()),
+    }
+  }
+}
+
+#[derive(Component, Debug)]
+pub struct EnemyAi {
+  pub state: AiState,
+  pub params: AiParams,
+  // time left in states that have a fixed length
+  pub timer: Timer,
+  pub charge_cooldown: Timer,
+  pub charge_direction: Vec2,
+  heading: f32,
+}
+
+impl EnemyAi {
+  pub fn new(params: AiParams) -> Self {
+    EnemyAi {
+      state: AiState::Idle,
+      params,
+      timer: Timer::from_seconds(params.idle_time, TimerMode::Once),
+      charge_cooldown: Timer::from_seconds(
+        params.charge.map_or(0.0, |charge| charge.cooldown),
+        TimerMode::Once,
+      ),
+      charge_direction: Vec2::ZERO,
+      // picked by update_enemy_ai once it starts wandering
+      heading: 0.0,
+    }
+  }
+
+  fn enter(&mut self, state: AiState, duration: f32) {
+    self.state = state;
+    self.timer = Timer::from_seconds(duration, TimerMode::Once);
+  }
+
+  // where to go once nothing more specific is going on
+  fn roam(&mut self, in_sight: bool) {
+    self.enter(
+      if in_sight {
+        AiState::Chase
+      } else {
+        AiState::Wander
+      },
+      0.0,
+    );
+  }
+}
+
+pub fn update_enemy_ai(
+  mut enemies: Query<
+    (
+      Entity,
+      &Transform,
+      &mut Velocity,
+      &mut EnemyAi,
+      &mut TextureAtlasSprite,
+      &Health,
+      &Stagger,
+      &SpeedMultiplier,
+      Option<&EnemyScaling>,
+    ),
+    (With<Enemy>, Without<Player>),
+  >,
+  index: Res<SpatialIndex>,
+  navigation: Res<Navigation>,
+  rapier_context: Res<RapierContext>,
+  time: Res<GameTime>,
+  mut rng: ResMut<GameRng>,
+) {
+  // velocities from the last frame, neighbours are looked up in the spatial index
+  let velocities: HashMap<Entity, Vec2> = enemies
+    .iter()
+    .map(|(entity, _, velocity, ..)| (entity, velocity.linvel))
+    .collect();
+
+  for (entity, transform, mut velocity, mut ai, mut sprite, health, stagger, multiplier, scaling) in
+    enemies.iter_mut()
+  {
+    let speed_scale = scaling.map_or(1.0, |scaling| scaling.speed) * multiplier.0;
+    let params = ai.params;
+
+    ai.charge_cooldown.tick(time.delta());
+    let timer_done = ai.timer.tick(time.delta()).finished();
+
+    let target = closest_player(transform, &index);
+    let offset = target.map(|player| player - transform.translation);
+    let distance = offset.map(|offset| offset.length());
+    let direction = offset.map_or(Vec3::ZERO, |offset| offset.normalize_or_zero());
+
+    let in_sight = distance.map_or(false, |distance| distance <= params.sight_range);
+    // a little slack before giving up on a chase
+    let lost_sight = distance.map_or(true, |distance| distance > params.sight_range * 1.5);
+    let low_health = health.current_health < health.max_health * params.flee_below;
+    let charge = params.charge.filter(|charge| {
+      ai.charge_cooldown.finished() && distance.map_or(false, |distance| distance <= charge.range)
+    });
+
+    let previous = ai.state;
+    match previous {
+      AiState::Idle => {
+        if timer_done {
+          ai.roam(in_sight);
+        }
+      }
+      AiState::Wander | AiState::Chase => {
+        if low_health {
+          ai.enter(AiState::Flee, 0.0);
+        } else if let Some(charge) = charge {
+          ai.charge_direction = Vec2::new(direction.x, direction.y) * charge.speed * speed_scale;
+          ai.enter(AiState::Windup, charge.windup);
+        } else if previous == AiState::Wander && in_sight {
+          ai.enter(AiState::Chase, 0.0);
+        } else if previous == AiState::Chase && lost_sight {
+          ai.enter(AiState::Wander, 0.0);
+        }
+      }
+      AiState::Windup => {
+        if timer_done {
+          let duration = params.charge.map_or(0.0, |charge| charge.duration);
+          ai.enter(AiState::Charge, duration);
+        }
+      }
+      AiState::Charge => {
+        if timer_done {
+          let recover = params.charge.map_or(0.0, |charge| charge.recover);
+          ai.charge_cooldown.reset();
+          ai.enter(AiState::Recover, recover);
+        }
+      }
+      AiState::Recover => {
+        if timer_done {
+          ai.roam(in_sight);
+        }
+      }
+      AiState::Flee => {
+        if !low_health {
+          ai.roam(in_sight);
+        }
+      }
+    }
+
+    if ai.state == AiState::Wander && previous != AiState::Wander {
+      ai.heading = rng.gen_range(0.0..std::f32::consts::TAU);
+    }
+
+    // the hit flash wins, it resets the colour itself once it's over
+    if !health.is_flashing() {
+      if ai.state == AiState::Windup {
+        let blink = (time.elapsed_seconds() * 12.0).sin() > 0.0;
+        sprite.color = if blink { WINDUP_COLOR } else { Color::WHITE };
+      } else if previous == AiState::Windup {
+        sprite.color = Color::WHITE;
+      }
+    }
+
+    // knocked back enemies keep thinking but can't move on their own
+    if stagger.is_staggered() {
+      continue;
+    }
+
+    let state = ai.state;
+    velocity.linvel = match state {
+      AiState::Idle | AiState::Windup | AiState::Recover => Vec2::ZERO,
+      AiState::Wander => {
+        ai.heading += rng.gen_range(-0.3..0.3);
+        let speed = rng.gen_range(0.0..params.wander_speed) * speed_scale;
+        Vec2::new(ai.heading.cos(), ai.heading.sin()) * speed
+      }
+      AiState::Chase => {
+        // closing in follows the path around obstacles
+        let path = navigation
+          .direction(transform.translation.truncate())
+          .unwrap_or_else(|| direction.truncate());
+        let direction = match (params.keep_distance, distance) {
+          // back off when too close, close in when too far and circle around in between
+          (Some(keep), Some(distance)) if distance < keep - 20.0 => -direction.truncate(),
+          (Some(keep), Some(distance)) if distance <= keep + 20.0 => {
+            Vec2::new(-direction.y, direction.x)
+          }
+          _ => path,
+        };
+        direction * params.chase_speed * speed_scale
+      }
+      AiState::Charge => ai.charge_direction,
+      AiState::Flee => Vec2::new(-direction.x, -direction.y) * params.chase_speed * speed_scale,
+    };
+
+    // charges go in a straight line, everything else spreads out a little
+    if let (AiState::Wander | AiState::Chase | AiState::Flee, Some(flocking)) =
+      (state, params.flocking)
+    {
+      velocity.linvel = flock(
+        entity,
+        transform.translation.truncate(),
+        velocity.linvel,
+        params.chase_speed * speed_scale,
+        &flocking,
+        &index,
+        &velocities,
+      );
+    }
+
+    if state != AiState::Idle {
+      velocity.linvel = avoid_obstacles(
+        transform.translation.truncate(),
+        velocity.linvel,
+        &rapier_context,
+      );
+    }
+  }
+}
+
+// slide along obstacles that are right ahead instead of pushing into them
+fn avoid_obstacles(position: Vec2, velocity: Vec2, rapier_context: &RapierContext) -> Vec2 {
+  let speed = velocity.length();
+  if speed == 0.0 {
+    return velocity;
+  }
+
+  let direction = velocity / speed;
+  // obstacles and props
+  let obstacles = QueryFilter::new()
+    .exclude_sensors()
+    .groups(CollisionGroups::new(Group::GROUP_3, Group::GROUP_7.union(Group::GROUP_10)).into());
+
+  match rapier_context.cast_ray_and_get_normal(position, direction, 30.0, true, obstacles) {
+    Some((_, hit)) if hit.toi > 0.0 => {
+      // keep going along the surface, whichever way is closer to where the enemy wanted to go
+      let tangent = hit.normal.perp();
+      let tangent = if tangent.dot(direction) < 0.0 {
+        -tangent
+      } else {
+        tangent
+      };
+      (tangent + hit.normal * 0.3).normalize_or_zero() * speed
+    }
+    _ => velocity,
+  }
+}
+
+fn flock(
+  entity: Entity,
+  position: Vec2,
+  velocity: Vec2,
+  speed: f32,
+  flocking: &FlockingParams,
+  index: &SpatialIndex,
+  velocities: &HashMap<Entity, Vec2>,
+) -> Vec2 {
+  let mut separation = Vec2::ZERO;
+  let mut center = Vec2::ZERO;
+  let mut heading = Vec2::ZERO;
+  let mut count = 0;
+
+  for (other, other_position) in index.enemies.query_radius(position, flocking.radius) {
+    if other == entity {
+      continue;
+    }
+
+    // the closer the neighbour the harder the push
+    let distance = position.distance(other_position);
+    separation +=
+      (position - other_position).normalize_or_zero() * (1.0 - distance / flocking.radius);
+    center += other_position;
+    heading += velocities.get(&other).copied().unwrap_or_default();
+    count += 1;
+  }
+
+  if count == 0 {
+    return velocity;
+  }
+
+  let center = center / count as f32;
+  let heading = heading / count as f32;
+
+  let steering = separation * flocking.separation * speed
+    + (center - position) / flocking.radius * flocking.cohesion * speed
+    + (heading - velocity) * flocking.alignment;
+
+  // steering changes the direction but never makes an enemy faster than it wants to be
+  (velocity + steering).clamp_length_max(velocity.length().max(speed))
+}
@@ -1,7 +1,11 @@
+use crate::map::Arena;
 use crate::player::Player;
 use crate::GameData;
 use bevy::prelude::*;
 
+// how much of the outside of the arena the camera may show
+const CAMERA_MARGIN: f32 = 100.0;
+
 pub fn setup_camera(mut commands: Commands) {
   commands.spawn(Camera2dBundle::default());
 }
@@ -16,6 +20,7 @@ pub fn follow_camera(
   >,
   player_query: Query<&Transform, With<Player>>,
   mut state: ResMut<GameData>,
+  arena: Res<Arena>,
   time: Res<Time>,
 ) {
   let sum_position: Vec3 = player_query
@@ -30,17 +35,37 @@ pub fn follow_camera(
     state.camera_pos = average_position_v2;
   }
 
-  for (_, mut pos) in camera_query.iter_mut() {
+  let (arena_min, arena_max) = arena.bounds();
+
+  for (projection, mut pos) in camera_query.iter_mut() {
     let camera_v2 = Vec2::new(pos.translation.x, pos.translation.y);
     let distance = state.camera_pos.distance(camera_v2);
 
-    if distance == 0.0 {
-      continue;
+    if distance != 0.0 {
+      let direction = (state.camera_pos - camera_v2).normalize_or_zero();
+
+      pos.translation.x += direction.x * distance * time.delta_seconds();
+      pos.translation.y += direction.y * distance * time.delta_seconds();
     }
 
-    let direction = (state.camera_pos - camera_v2).normalize_or_zero();
+    // show at most a strip of the killzone, arenas smaller than the screen stay centered
+    let half_view = Vec2::new(
+      projection.right - projection.left,
+      projection.top - projection.bottom,
+    ) * projection.scale
+      / 2.0;
+    let low = arena_min + half_view - CAMERA_MARGIN;
+    let high = arena_max - half_view + CAMERA_MARGIN;
+    let center = (arena_min + arena_max) / 2.0;
+    pos.translation.x = clamp_axis(pos.translation.x, low.x, high.x, center.x);
+    pos.translation.y = clamp_axis(pos.translation.y, low.y, high.y, center.y);
+  }
+}
 
-    pos.translation.x += direction.x * distance * time.delta_seconds();
-    pos.translation.y += direction.y * distance * time.delta_seconds();
+fn clamp_axis(value: f32, low: f32, high: f32, center: f32) -> f32 {
+  if low > high {
+    center
+  } else {
+    value.clamp(low, high)
   }
 }
@@ -0,0 +1,124 @@
+use crate::components::*;
+use crate::fixed::{GameTime, Interpolated};
+use crate::pool::{EntityPools, PoolKind, Pooled};
+use crate::spatial::SpatialIndex;
+use crate::{DespawnEvent, GameData};
+use bevy::prelude::*;
+use rand::prelude::*;
+
+const COIN_LIFETIME: f32 = 12.0;
+// coins start blinking this long before they disappear
+const BLINK_TIME: f32 = 3.0;
+const PICKUP_RADIUS: f32 = 16.0;
+const MAGNET_SPEED: f32 = 250.0;
+const MAX_COINS: i32 = 5;
+
+#[derive(Component, Debug)]
+pub struct Coin {
+  pub value: i32,
+}
+
+// coins closer than this fly towards the player
+#[derive(Component, Debug)]
+pub struct Magnet {
+  pub radius: f32,
+}
+
+// splits the money over a few coins scattered around the position
+pub fn drop_coins(
+  commands: &mut Commands,
+  asset_server: &AssetServer,
+  pools: &mut EntityPools,
+  pooled: &mut Query<&mut Pooled>,
+  rng: &mut impl Rng,
+  position: Vec3,
+  value: i32,
+) {
+  if value <= 0 {
+    return;
+  }
+
+  let count = value.clamp(1, MAX_COINS);
+
+  for i in 0..count {
+    // whatever doesn't split evenly goes on the first coin
+    let value = value / count + if i == 0 { value % count } else { 0 };
+    let angle = rng.gen_range(0.0..std::f32::consts::TAU);
+    let distance = if count > 1 {
+      rng.gen_range(5.0..15.0)
+    } else {
+      0.0
+    };
+    let offset = Vec2::new(angle.cos(), angle.sin()) * distance;
+    let transform = Transform::from_translation((position.truncate() + offset).extend(0.07));
+
+    let coin = pools.acquire(PoolKind::Coin, pooled).unwrap_or_else(|| {
+      commands
+        .spawn((
+          OnGameScreen,
+          Pooled::new(PoolKind::Coin),
+          SpriteBundle {
+            texture: asset_server.load("coin.png"),
+            ..default()
+          },
+        ))
+        .id()
+    });
+
+    commands.entity(coin).insert((
+      Coin { value },
+      transform,
+      Interpolated::new(transform.translation),
+      Visibility::VISIBLE,
+      Sprite::default(),
+      ExpirationTimer(Timer::from_seconds(COIN_LIFETIME, TimerMode::Once)),
+    ));
+  }
+}
+
+pub fn collect_coins(
+  mut coins: Query<(
+    Entity,
+    &Coin,
+    &Pooled,
+    &mut Transform,
+    &mut Sprite,
+    &ExpirationTimer,
+  )>,
+  magnets: Query<&Magnet>,
+  index: Res<SpatialIndex>,
+  mut state: ResMut<GameData>,
+  mut despawn_events: EventWriter<DespawnEvent>,
+  time: Res<GameTime>,
+) {
+  for (entity, coin, pooled, mut transform, mut sprite, expiration) in coins.iter_mut() {
+    if !pooled.active {
+      continue;
+    }
+
+    let remaining = expiration.0.duration().as_secs_f32() - expiration.0.elapsed_secs();
+    let visible = remaining > BLINK_TIME || (remaining * 8.0) as i32 % 2 == 0;
+    sprite.color.set_a(if visible { 1.0 } else { 0.3 });
+
+    let position = transform.translation.truncate();
+    let (player, player_position) = match index.players.nearest(position) {
+      Some(player) => player,
+      None => continue,
+    };
+    let distance = position.distance(player_position);
+
+    if distance < PICKUP_RADIUS {
+      state.money += coin.value;
+      despawn_events.send(DespawnEvent { entity });
+      continue;
+    }
+
+    let radius = magnets.get(player).map_or(0.0, |magnet| magnet.radius);
+    if distance < radius {
+      let step = (MAGNET_SPEED * time.delta_seconds()).min(distance);
+      let position = position + (player_position - position).normalize_or_zero() * step;
+      transform.translation.x = position.x;
+      transform.translation.y = position.y;
+    }
+  }
+}
@@ -1,4 +1,5 @@
 use bevy::prelude::*;
+use rand::prelude::*;
 
 #[derive(Component)]
 pub struct OnGameScreen;
@@ -6,6 +7,10 @@ pub struct OnGameScreen;
 #[derive(Component)]
 pub struct Killzone;
 
+// rocks, walls and pillars, nothing moves or shoots through them
+#[derive(Component)]
+pub struct Obstacle;
+
 #[derive(Component, Deref, DerefMut)]
 pub struct AnimationTimer(pub Timer);
 
@@ -31,4 +36,71 @@ impl Health {
       },
     }
   }
+
+  // true while the sprite shows the hit flash, nothing else should tint it then
+  pub fn is_flashing(&self) -> bool {
+    !self.dmg_timer.paused() && !self.dmg_timer.finished()
+  }
+}
+
+// fraction of incoming damage that is ignored, e.g. while inside a shield aura
+#[derive(Component, Default, Debug)]
+pub struct DamageReduction(pub f32);
+
+impl DamageReduction {
+  pub fn apply(&self, damage: f32) -> f32 {
+    damage * (1.0 - self.0)
+  }
+}
+
+// how much faster or slower than normal something moves, set by hazard zones
+#[derive(Component, Debug)]
+pub struct SpeedMultiplier(pub f32);
+
+impl Default for SpeedMultiplier {
+  fn default() -> Self {
+    SpeedMultiplier(1.0)
+  }
+}
+
+#[derive(Component, Debug)]
+pub struct Stagger {
+  pub resistance: f32,
+  pub timer: Timer,
+}
+
+impl Stagger {
+  pub fn new(resistance: f32) -> Self {
+    Stagger {
+      resistance,
+      timer: {
+        let mut t = Timer::from_seconds(0.25, TimerMode::Once);
+        t.pause();
+        t
+      },
+    }
+  }
+
+  pub fn is_staggered(&self) -> bool {
+    !self.timer.paused() && !self.timer.finished()
+  }
+}
+
+#[derive(Component, Debug, Clone, Copy)]
+pub struct Critical {
+  pub chance: f32,
+  pub multiplier: f32,
+}
+
+impl Critical {
+  // rolls +-10% damage variance and a crit, returns the final damage and whether it crit
+  pub fn roll(&self, damage: f32, rng: &mut impl Rng) -> (f32, bool) {
+    let damage = damage * rng.gen_range(0.9..1.1);
+
+    if rng.gen::<f32>() < self.chance {
+      (damage * self.multiplier, true)
+    } else {
+      (damage, false)
+    }
+  }
 }
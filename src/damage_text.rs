@@ -0,0 +1,176 @@
+use crate::components::*;
+use crate::fixed::GameTime;
+use crate::pool::{EntityPools, PoolKind, Pooled};
+use crate::{DamageEvent, DamageSource, Settings};
+use bevy::prelude::*;
+use bevy::utils::HashMap;
+use rand::prelude::*;
+
+#[derive(Component)]
+pub struct FloatingText {
+  pub velocity: Vec2,
+}
+
+// continuous damage such as the laser is summed up and shown periodically
+#[derive(Resource)]
+pub struct DamageTextAccumulator {
+  pub timer: Timer,
+  pub pending: HashMap<Entity, (f32, bool)>,
+}
+
+impl Default for DamageTextAccumulator {
+  fn default() -> Self {
+    DamageTextAccumulator {
+      timer: Timer::from_seconds(0.3, TimerMode::Repeating),
+      pending: HashMap::default(),
+    }
+  }
+}
+
+fn spawn_floating_text(
+  commands: &mut Commands,
+  asset_server: &Res<AssetServer>,
+  pools: &mut EntityPools,
+  pooled: &mut Query<&mut Pooled>,
+  translation: Vec3,
+  damage: f32,
+  crit: bool,
+) {
+  // only decoration, kept off the game rng so turning damage numbers off doesn't change a run
+  let mut rng = thread_rng();
+  let (color, font_size) = if crit {
+    (Color::YELLOW, 22.0)
+  } else {
+    (Color::WHITE, 16.0)
+  };
+
+  let text = pools
+    .acquire(PoolKind::DamageText, pooled)
+    .unwrap_or_else(|| {
+      commands
+        .spawn((
+          OnGameScreen,
+          Pooled::new(PoolKind::DamageText),
+          Text2dBundle::default(),
+        ))
+        .id()
+    });
+
+  commands.entity(text).insert((
+    FloatingText {
+      velocity: Vec2::new(rng.gen_range(-15.0..15.0), 40.0),
+    },
+    ExpirationTimer(Timer::from_seconds(0.8, TimerMode::Once)),
+    Text::from_section(
+      format!("{:.0}", damage.ceil()),
+      TextStyle {
+        font: asset_server.load("font.ttf"),
+        font_size,
+        color,
+      },
+    )
+    .with_alignment(TextAlignment::CENTER),
+    Transform::from_translation(Vec3::new(
+      translation.x,
+      translation.y + 16.0,
+      translation.z + 1.0,
+    )),
+    Visibility::VISIBLE,
+  ));
+}
+
+// show the damage that was actually dealt, shielded enemies take less
+fn reduced(damage: f32, reduction: Option<&DamageReduction>) -> f32 {
+  reduction.map_or(damage, |reduction| reduction.apply(damage))
+}
+
+#[allow(clippy::too_many_arguments)]
+pub fn spawn_damage_text(
+  mut commands: Commands,
+  asset_server: Res<AssetServer>,
+  mut damage_events: EventReader<DamageEvent>,
+  mut accumulator: ResMut<DamageTextAccumulator>,
+  settings: Res<Settings>,
+  mut pools: ResMut<EntityPools>,
+  mut pooled: Query<&mut Pooled>,
+  transforms: Query<(&Transform, Option<&DamageReduction>), With<Health>>,
+  time: Res<GameTime>,
+) {
+  if !settings.damage_numbers {
+    damage_events.clear();
+    accumulator.pending.clear();
+    return;
+  }
+
+  for damage_event in damage_events.iter() {
+    match damage_event.source {
+      DamageSource::Laser | DamageSource::Killzone | DamageSource::Hazard => {
+        let pending = accumulator
+          .pending
+          .entry(damage_event.entity)
+          .or_insert((0.0, false));
+        let reduction = transforms
+          .get(damage_event.entity)
+          .ok()
+          .and_then(|(_, reduction)| reduction);
+        pending.0 += reduced(damage_event.damage, reduction);
+        pending.1 |= damage_event.crit;
+      }
+      _ => {
+        if let Ok((transform, reduction)) = transforms.get(damage_event.entity) {
+          spawn_floating_text(
+            &mut commands,
+            &asset_server,
+            &mut pools,
+            &mut pooled,
+            transform.translation,
+            reduced(damage_event.damage, reduction),
+            damage_event.crit,
+          );
+        }
+      }
+    }
+  }
+
+  if accumulator.timer.tick(time.delta()).just_finished() {
+    for (entity, (damage, crit)) in accumulator.pending.drain() {
+      if let Ok((transform, _)) = transforms.get(entity) {
+        spawn_floating_text(
+          &mut commands,
+          &asset_server,
+          &mut pools,
+          &mut pooled,
+          transform.translation,
+          damage,
+          crit,
+        );
+      }
+    }
+  }
+}
+
+pub fn animate_floating_text(
+  time: Res<Time>,
+  mut query: Query<(
+    &mut Transform,
+    &mut Text,
+    &FloatingText,
+    &ExpirationTimer,
+    &Visibility,
+  )>,
+) {
+  for (mut transform, mut text, floating_text, expiration, visibility) in query.iter_mut() {
+    // pooled text waiting to be reused
+    if !visibility.is_visible {
+      continue;
+    }
+
+    transform.translation.x += floating_text.velocity.x * time.delta_seconds();
+    transform.translation.y += floating_text.velocity.y * time.delta_seconds();
+
+    let alpha = expiration.0.percent_left();
+    for section in text.sections.iter_mut() {
+      section.style.color.set_a(alpha);
+    }
+  }
+}
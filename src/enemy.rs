@@ -1,15 +1,34 @@
+use crate::ai::*;
+use crate::coins::drop_coins;
 use crate::components::*;
+use crate::fixed::{GameRng, GameTime, Interpolated};
+use crate::map::Arena;
 use crate::player::Player;
-use crate::GameData;
+use crate::pool::{EntityPools, Pooled};
+use crate::spatial::SpatialIndex;
+use crate::stats::RunStats;
+use crate::DespawnEvent;
 use crate::TextureAtlasHandles;
 use bevy::prelude::*;
+use bevy::utils::HashSet;
+use bevy_prototype_lyon::entity::ShapeBundle;
+use bevy_prototype_lyon::prelude::FillMode;
+use bevy_prototype_lyon::prelude::*;
 use bevy_rapier2d::prelude::*;
 use rand::prelude::*;
 
+// sprite scale of the support enemies, their range indicators are children and inherit it
+const SUPPORT_SCALE: f32 = 1.1;
+
+#[derive(Clone)]
 pub enum EnemySpawnerType {
   Normal,
   Elite,
   Boss,
+  Ranged,
+  Splitter,
+  Healer,
+  ShieldBearer,
 }
 
 #[derive(Component)]
@@ -19,12 +38,36 @@ pub struct EnemySpawner {
   pub spawn_count: usize,
   pub spawn_limit: usize,
   pub enemy_type: EnemySpawnerType,
+  pub pattern: SpawnPattern,
 }
 
-#[derive(Component)]
-pub enum EnemyMovement {
-  Homing,
-  Random(f32),
+// where the enemies of a single spawner tick appear
+#[derive(Clone, Copy, Debug)]
+pub enum SpawnPattern {
+  // one enemy at the spawner itself
+  Point,
+  // `count` enemies evenly spaced on a circle of `radius` around the players
+  Ring { count: usize, radius: f32 },
+  // `count` enemies scattered within `spread` of the spawner
+  Burst { count: usize, spread: f32 },
+  // `count` enemies just outside of the screen around the camera
+  Edge { count: usize },
+}
+
+// multipliers for the enemies a spawner creates, copied onto each spawned enemy
+#[derive(Component, Clone, Copy, Debug)]
+pub struct EnemyScaling {
+  pub health: f32,
+  pub speed: f32,
+}
+
+impl Default for EnemyScaling {
+  fn default() -> Self {
+    EnemyScaling {
+      health: 1.0,
+      speed: 1.0,
+    }
+  }
 }
 
 #[derive(Component)]
@@ -32,28 +75,103 @@ pub struct EnemyEliteSpawner {
   pub timer: Timer,
 }
 
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub enum EnemyKind {
+  Slime,
+  Elite,
+  Boss,
+  BossSpawn,
+  Ranged,
+  Splitter,
+  Healer,
+  ShieldBearer,
+}
+
+impl EnemyKind {
+  pub fn name(&self) -> &'static str {
+    match self {
+      EnemyKind::Slime => "Slime",
+      EnemyKind::Elite => "Elite slime",
+      EnemyKind::Boss => "Slime King",
+      EnemyKind::BossSpawn => "Slime spawn",
+      EnemyKind::Ranged => "Spitting slime",
+      EnemyKind::Splitter => "Splitting slime",
+      EnemyKind::Healer => "Healing slime",
+      EnemyKind::ShieldBearer => "Shield slime",
+    }
+  }
+}
+
 #[derive(Component, Debug)]
 pub struct Enemy {
   pub reward: i32,
+  pub kind: EnemyKind,
+}
+
+#[derive(Clone, Copy, Debug)]
+pub enum SplitChild {
+  Slime,
+  BossSpawn,
+}
+
+// on death the enemy bursts into `count` children, which split again while `depth` is above 0
+#[derive(Component, Clone, Copy, Debug)]
+pub struct SplitOnDeath {
+  pub child: SplitChild,
+  pub count: usize,
+  // relative to the scale of the dying enemy
+  pub scale: f32,
+  pub health: f32,
+  pub spread: f32,
+  pub depth: usize,
 }
 
 #[derive(Component, Debug)]
-pub struct Charge {
+pub struct RangedAttack {
   pub cooldown: Timer,
-  pub duration: Timer,
-  pub direction: Vec2,
+  pub damage: f32,
+  pub range: f32,
+  pub projectile_speed: f32,
+}
+
+// periodically heals every other enemy within the radius
+#[derive(Component, Debug)]
+pub struct Healer {
+  pub timer: Timer,
+  pub amount: f32,
+  pub radius: f32,
+}
+
+// other enemies within the radius take reduced damage
+#[derive(Component, Debug)]
+pub struct ShieldBearer {
+  pub reduction: f32,
+  pub radius: f32,
+}
+
+#[derive(Component, Debug)]
+pub struct EnemyProjectile {
+  pub damage: f32,
 }
 
 #[derive(Component, Debug)]
-pub struct Explode;
+pub struct Boss {
+  pub name: String,
+}
 
+#[allow(clippy::too_many_arguments)]
 pub fn generic_spawner(
   mut commands: Commands,
-  mut spawners: Query<(&Transform, &mut EnemySpawner)>,
+  mut spawners: Query<(&Transform, &mut EnemySpawner, Option<&EnemyScaling>)>,
+  players: Query<&Transform, With<Player>>,
+  cameras: Query<(&Transform, &OrthographicProjection), With<Camera2d>>,
+  windows: Res<Windows>,
+  arena: Res<Arena>,
   textures: Res<TextureAtlasHandles>,
-  time: Res<Time>,
+  time: Res<GameTime>,
+  mut rng: ResMut<GameRng>,
 ) {
-  for (transform, mut spawner) in spawners.iter_mut() {
+  for (transform, mut spawner, scaling) in spawners.iter_mut() {
     spawner.initial_delay.tick(time.delta());
 
     if !spawner.initial_delay.finished() {
@@ -62,88 +180,375 @@ pub fn generic_spawner(
 
     if spawner.timer.tick(time.delta()).just_finished() && spawner.spawn_count < spawner.spawn_limit
     {
-      let mut transform = Transform::from_translation(transform.translation);
-      transform.translation.z += 0.1;
+      let scaling = scaling.copied().unwrap_or_default();
+      let positions = spawn_positions(
+        spawner.pattern,
+        transform.translation,
+        &players,
+        &cameras,
+        &windows,
+        &arena,
+        &mut *rng,
+      );
+      let remaining = spawner.spawn_limit - spawner.spawn_count;
+
+      for position in positions.into_iter().take(remaining) {
+        let mut transform = Transform::from_translation(position);
+        transform.translation.z = 0.1;
 
-      spawner.spawn_count += 1;
-      match spawner.enemy_type {
-        EnemySpawnerType::Elite => spawn_enemy(
+        spawner.spawn_count += 1;
+        let enemy = spawn_enemy_type(
           &mut commands,
-          transform.with_scale(Vec3::new(1.25, 1.25, 1.25)),
-          textures.elite_atlas_handle.clone(),
-          500.0,
-          EnemyMovement::Homing,
-          5,
-        ),
-        EnemySpawnerType::Normal => {
-          let mut rng = thread_rng();
-          spawn_enemy(
-            &mut commands,
-            transform,
-            textures.atlas_handle.clone(),
-            100.0,
-            EnemyMovement::Random(rng.gen_range(0.0..std::f32::consts::PI * 2.0)),
-            1,
-          );
-        }
-        EnemySpawnerType::Boss => {
-          commands
-            .spawn((
-              OnGameScreen,
-              EnemyMovement::Homing,
-              Enemy { reward: 200 },
-              Health::new(2000.0),
-              Charge {
-                cooldown: Timer::from_seconds(8.0, TimerMode::Repeating),
-                duration: {
-                  let mut t = Timer::from_seconds(2.0, TimerMode::Once);
-                  t.pause();
-                  t
-                },
-                direction: Vec2::default(),
-              },
-              Explode,
-              ActiveEvents::COLLISION_EVENTS,
-              CollisionGroups::new(Group::GROUP_3, Group::ALL),
-              SpriteSheetBundle {
-                texture_atlas: textures.boss_atlas_handle.clone(),
-                transform: transform.with_scale(Vec3::new(3.0, 3.0, 3.0)),
-                ..default()
-              },
-              AnimationTimer(Timer::from_seconds(0.3, TimerMode::Repeating)),
-            ))
-            .insert((
-              RigidBody::Dynamic,
-              Velocity {
-                linvel: Vec2::new(20., 20.),
-                angvel: 0.0,
-              },
-              Damping {
-                linear_damping: 0.0,
-                angular_damping: 100000.0,
-              },
-              Collider::ball(12.),
-              Restitution::coefficient(0.7),
-            ));
-        }
+          &textures,
+          &spawner.enemy_type,
+          transform,
+          scaling,
+        );
+        commands.entity(enemy).insert(scaling);
+      }
+    }
+  }
+}
+
+fn spawn_positions(
+  pattern: SpawnPattern,
+  origin: Vec3,
+  players: &Query<&Transform, With<Player>>,
+  cameras: &Query<(&Transform, &OrthographicProjection), With<Camera2d>>,
+  windows: &Windows,
+  arena: &Arena,
+  rng: &mut impl Rng,
+) -> Vec<Vec3> {
+  // keep everything out of the killzone
+  let clamp = |p: Vec3| arena.clamp(p.truncate(), 20.0).extend(p.z);
+
+  match pattern {
+    SpawnPattern::Point => vec![origin],
+    SpawnPattern::Ring { count, radius } => {
+      let player_count = players.iter().count();
+      let center = if player_count == 0 {
+        origin
+      } else {
+        players.iter().map(|t| t.translation).sum::<Vec3>() / player_count as f32
       };
+      let offset = rng.gen_range(0.0..std::f32::consts::TAU);
+
+      (0..count)
+        .map(|i| {
+          let angle = offset + i as f32 * std::f32::consts::TAU / count as f32;
+          clamp(center + Vec3::new(angle.cos(), angle.sin(), 0.0) * radius)
+        })
+        .collect()
+    }
+    SpawnPattern::Burst { count, spread } => (0..count)
+      .map(|_| {
+        let angle = rng.gen_range(0.0..std::f32::consts::TAU);
+        let distance = rng.gen_range(0.0..=spread);
+        clamp(origin + Vec3::new(angle.cos(), angle.sin(), 0.0) * distance)
+      })
+      .collect(),
+    SpawnPattern::Edge { count } => {
+      let (center, scale) = cameras
+        .iter()
+        .next()
+        .map_or((origin, 1.0), |(transform, projection)| {
+          (transform.translation, projection.scale)
+        });
+      let (width, height) = windows
+        .get_primary()
+        .map_or((1280.0, 720.0), |window| (window.width(), window.height()));
+      // a little margin so the enemies are not visible when they appear
+      let half = Vec2::new(width, height) * scale / 2.0 + 32.0;
+
+      (0..count)
+        .map(|_| {
+          let side = rng.gen_range(0..4);
+          let offset = match side {
+            0 => Vec2::new(rng.gen_range(-half.x..half.x), half.y),
+            1 => Vec2::new(rng.gen_range(-half.x..half.x), -half.y),
+            2 => Vec2::new(half.x, rng.gen_range(-half.y..half.y)),
+            _ => Vec2::new(-half.x, rng.gen_range(-half.y..half.y)),
+          };
+          clamp(center + offset.extend(0.0))
+        })
+        .collect()
     }
   }
 }
 
+fn spawn_enemy_type(
+  commands: &mut Commands,
+  textures: &TextureAtlasHandles,
+  enemy_type: &EnemySpawnerType,
+  transform: Transform,
+  scaling: EnemyScaling,
+) -> Entity {
+  match enemy_type {
+    EnemySpawnerType::Elite => spawn_enemy(
+      commands,
+      transform.with_scale(Vec3::new(1.25, 1.25, 1.25)),
+      textures.elite_atlas_handle.clone(),
+      500.0 * scaling.health,
+      EnemyAi::new(AiParams {
+        sight_range: f32::INFINITY,
+        flocking: Some(FlockingParams {
+          radius: 60.0,
+          separation: 2.0,
+          alignment: 0.3,
+          cohesion: 0.1,
+        }),
+        ..default()
+      }),
+      Enemy {
+        reward: 5,
+        kind: EnemyKind::Elite,
+      },
+      0.5,
+    ),
+    EnemySpawnerType::Normal => spawn_enemy(
+      commands,
+      transform,
+      textures.atlas_handle.clone(),
+      100.0 * scaling.health,
+      EnemyAi::new(AiParams::default()),
+      Enemy {
+        reward: 1,
+        kind: EnemyKind::Slime,
+      },
+      0.0,
+    ),
+    EnemySpawnerType::Ranged => {
+      let enemy = spawn_enemy(
+        commands,
+        transform.with_scale(Vec3::new(0.9, 0.9, 0.9)),
+        textures.elite_atlas_handle.clone(),
+        150.0 * scaling.health,
+        EnemyAi::new(AiParams {
+          sight_range: f32::INFINITY,
+          keep_distance: Some(200.0),
+          flee_below: 0.25,
+          ..default()
+        }),
+        Enemy {
+          reward: 3,
+          kind: EnemyKind::Ranged,
+        },
+        0.2,
+      );
+      commands.entity(enemy).insert(RangedAttack {
+        cooldown: Timer::from_seconds(2.5, TimerMode::Repeating),
+        damage: 10.0,
+        range: 350.0,
+        projectile_speed: 200.0,
+      });
+      enemy
+    }
+    EnemySpawnerType::Splitter => {
+      let enemy = spawn_enemy(
+        commands,
+        transform.with_scale(Vec3::new(1.4, 1.4, 1.4)),
+        textures.atlas_handle.clone(),
+        200.0 * scaling.health,
+        EnemyAi::new(AiParams {
+          sight_range: f32::INFINITY,
+          flocking: Some(FlockingParams {
+            radius: 60.0,
+            separation: 1.5,
+            alignment: 0.3,
+            cohesion: 0.1,
+          }),
+          ..default()
+        }),
+        Enemy {
+          reward: 2,
+          kind: EnemyKind::Splitter,
+        },
+        0.2,
+      );
+      commands.entity(enemy).insert(SplitOnDeath {
+        child: SplitChild::Slime,
+        count: 3,
+        scale: 0.7,
+        health: 60.0,
+        spread: 15.0,
+        depth: 1,
+      });
+      enemy
+    }
+    EnemySpawnerType::Healer => {
+      let enemy = spawn_enemy(
+        commands,
+        transform.with_scale(Vec3::splat(SUPPORT_SCALE)),
+        textures.atlas_handle.clone(),
+        150.0 * scaling.health,
+        EnemyAi::new(AiParams {
+          sight_range: f32::INFINITY,
+          keep_distance: Some(260.0),
+          flee_below: 0.4,
+          ..default()
+        }),
+        Enemy {
+          reward: 4,
+          kind: EnemyKind::Healer,
+        },
+        0.2,
+      );
+      let radius = 150.0;
+      commands
+        .entity(enemy)
+        .insert(Healer {
+          timer: Timer::from_seconds(3.0, TimerMode::Repeating),
+          amount: 40.0,
+          radius,
+        })
+        .with_children(|parent| {
+          parent.spawn(range_indicator(
+            radius / SUPPORT_SCALE,
+            Color::rgb(0.2, 0.9, 0.3),
+          ));
+        });
+      enemy
+    }
+    EnemySpawnerType::ShieldBearer => {
+      let enemy = spawn_enemy(
+        commands,
+        transform.with_scale(Vec3::splat(SUPPORT_SCALE)),
+        textures.elite_atlas_handle.clone(),
+        300.0 * scaling.health,
+        EnemyAi::new(AiParams {
+          sight_range: f32::INFINITY,
+          chase_speed: 50.0,
+          // stays close to the pack it is shielding
+          flocking: Some(FlockingParams {
+            radius: 120.0,
+            separation: 1.0,
+            alignment: 0.2,
+            cohesion: 0.6,
+          }),
+          ..default()
+        }),
+        Enemy {
+          reward: 4,
+          kind: EnemyKind::ShieldBearer,
+        },
+        0.6,
+      );
+      let radius = 120.0;
+      commands
+        .entity(enemy)
+        .insert(ShieldBearer {
+          reduction: 0.5,
+          radius,
+        })
+        .with_children(|parent| {
+          parent.spawn(range_indicator(
+            radius / SUPPORT_SCALE,
+            Color::rgb(0.3, 0.5, 1.0),
+          ));
+        });
+      enemy
+    }
+    EnemySpawnerType::Boss => commands
+      .spawn((
+        OnGameScreen,
+        EnemyAi::new(AiParams {
+          idle_time: 1.0,
+          sight_range: f32::INFINITY,
+          // too big to be pushed around by other slimes
+          flocking: None,
+          charge: Some(ChargeParams {
+            cooldown: 8.0,
+            range: f32::INFINITY,
+            windup: 1.0,
+            duration: 2.0,
+            recover: 0.75,
+            speed: 300.0,
+          }),
+          ..default()
+        }),
+        Enemy {
+          reward: 200,
+          kind: EnemyKind::Boss,
+        },
+        Health::new(2000.0 * scaling.health),
+        SplitOnDeath {
+          child: SplitChild::BossSpawn,
+          count: 30,
+          scale: 0.25,
+          health: 100.0,
+          spread: 30.0,
+          depth: 0,
+        },
+        Boss {
+          name: "Slime King".into(),
+        },
+        ActiveEvents::COLLISION_EVENTS,
+        CollisionGroups::new(Group::GROUP_3, Group::ALL),
+        SpriteSheetBundle {
+          texture_atlas: textures.boss_atlas_handle.clone(),
+          transform: transform.with_scale(Vec3::new(3.0, 3.0, 3.0)),
+          ..default()
+        },
+        AnimationTimer(Timer::from_seconds(0.3, TimerMode::Repeating)),
+      ))
+      .insert((
+        RigidBody::Dynamic,
+        Velocity {
+          linvel: Vec2::new(20., 20.),
+          angvel: 0.0,
+        },
+        Damping {
+          linear_damping: 0.0,
+          angular_damping: 100000.0,
+        },
+        Collider::ball(12.),
+        Restitution::coefficient(0.7),
+        ExternalImpulse::default(),
+        ReadMassProperties::default(),
+        Stagger::new(0.95),
+        DamageReduction::default(),
+        SpeedMultiplier::default(),
+        Interpolated::new(transform.translation),
+      ))
+      .id(),
+  }
+}
+
+// translucent circle drawn around support enemies, `radius` is in the parent's local scale
+fn range_indicator(radius: f32, color: Color) -> ShapeBundle {
+  let shape = shapes::Circle {
+    radius,
+    center: Vec2::ZERO,
+  };
+  let mut fill = color;
+  fill.set_a(0.08);
+  let mut outline = color;
+  outline.set_a(0.5);
+
+  GeometryBuilder::build_as(
+    &shape,
+    DrawMode::Outlined {
+      fill_mode: FillMode::color(fill),
+      outline_mode: StrokeMode::new(outline, 1.0),
+    },
+    Transform::from_translation(Vec3::new(0.0, 0.0, -0.05)),
+  )
+}
+
 pub fn spawn_enemy(
   commands: &mut Commands,
   transform: Transform,
   texture_atlas: Handle<TextureAtlas>,
   health: f32,
-  movement: EnemyMovement,
-  reward: i32,
-) {
+  ai: EnemyAi,
+  enemy: Enemy,
+  knockback_resistance: f32,
+) -> Entity {
   commands
     .spawn((
       OnGameScreen,
-      movement,
-      Enemy { reward },
+      ai,
+      enemy,
       Health::new(health),
       ActiveEvents::COLLISION_EVENTS,
       CollisionGroups::new(Group::GROUP_3, Group::ALL),
@@ -166,144 +571,348 @@ pub fn spawn_enemy(
       },
       Collider::ball(12.),
       Restitution::coefficient(0.7),
+      ExternalImpulse::default(),
+      ReadMassProperties::default(),
+      Stagger::new(knockback_resistance),
+      DamageReduction::default(),
+      SpeedMultiplier::default(),
+      Interpolated::new(transform.translation),
+    ))
+    .id()
+}
+
+pub fn closest_player(transform: &Transform, index: &SpatialIndex) -> Option<Vec3> {
+  index
+    .players
+    .nearest(transform.translation.truncate())
+    .map(|(_, position)| position.extend(0.0))
+}
+
+pub fn enemy_shooting(
+  mut commands: Commands,
+  mut shooters: Query<(&Transform, &mut RangedAttack, &Stagger)>,
+  index: Res<SpatialIndex>,
+  asset_server: Res<AssetServer>,
+  time: Res<GameTime>,
+) {
+  for (transform, mut attack, stagger) in shooters.iter_mut() {
+    if !attack.cooldown.tick(time.delta()).just_finished() || stagger.is_staggered() {
+      continue;
+    }
+
+    let player = match closest_player(transform, &index) {
+      Some(player) if player.distance(transform.translation) <= attack.range => player,
+      _ => continue,
+    };
+    let direction = (player - transform.translation).normalize_or_zero();
+
+    let mut projectile_transform = Transform::from_translation(transform.translation);
+    projectile_transform.translation += Vec3::new(direction.x * 20.0, direction.y * 20.0, 0.0);
+
+    commands.spawn((
+      OnGameScreen,
+      SpriteBundle {
+        texture: asset_server.load("projectile.png"),
+        sprite: Sprite {
+          color: Color::rgb(0.9, 0.3, 0.9),
+          ..default()
+        },
+        transform: projectile_transform,
+        ..default()
+      },
+      // enemy projectiles only hit players and obstacles
+      CollisionGroups::new(Group::GROUP_4, Group::GROUP_1.union(Group::GROUP_7)),
+      EnemyProjectile {
+        damage: attack.damage,
+      },
+      ExpirationTimer(Timer::from_seconds(4.0, TimerMode::Once)),
+      ActiveEvents::COLLISION_EVENTS,
+      RigidBody::Dynamic,
+      Velocity {
+        linvel: Vec2::new(direction.x, direction.y) * attack.projectile_speed,
+        angvel: 0.0,
+      },
+      Collider::ball(3.),
+      Interpolated::new(projectile_transform.translation),
     ));
+  }
 }
 
-fn closest_player(
-  transform: &Transform,
-  players: &Query<&Transform, With<Player>>,
-) -> Option<Vec3> {
-  let mut closest_player: Option<Vec3> = None;
-
-  for player in players.iter() {
-    match closest_player {
-      Some(p) => {
-        if transform.translation.distance(player.translation) < transform.translation.distance(p) {
-          closest_player = Some(player.translation);
-        }
+pub fn heal_allies(
+  mut healers: Query<(Entity, &Transform, &mut Healer)>,
+  mut enemies: Query<(Entity, &Transform, &mut Health), With<Enemy>>,
+  time: Res<GameTime>,
+) {
+  for (healer_entity, healer_transform, mut healer) in healers.iter_mut() {
+    if !healer.timer.tick(time.delta()).just_finished() {
+      continue;
+    }
+
+    for (entity, transform, mut health) in enemies.iter_mut() {
+      // dying enemies can't be saved
+      if entity == healer_entity || health.current_health <= 0.0 {
+        continue;
+      }
+      if transform.translation.distance(healer_transform.translation) <= healer.radius {
+        health.current_health = (health.current_health + healer.amount).min(health.max_health);
       }
-      None => closest_player = Some(player.translation),
     }
   }
-
-  closest_player
 }
 
-pub fn enemy_movement(
-  mut query: Query<(&mut Transform, &mut Velocity, &mut EnemyMovement), Without<Player>>,
-  players: Query<&Transform, With<Player>>,
+pub fn update_shields(
+  bearers: Query<(Entity, &Transform, &ShieldBearer)>,
+  mut enemies: Query<(Entity, &Transform, &mut DamageReduction), With<Enemy>>,
 ) {
-  let mut rng = thread_rng();
-  for (transform, mut velocity, mut movement) in query.iter_mut() {
-    match *movement {
-      EnemyMovement::Homing => {
-        let closest_player = closest_player(&transform, &players);
-
-        let speed = 60.0;
-
-        match closest_player {
-          Some(player) => {
-            let direction = (player - transform.translation).normalize_or_zero();
-            velocity.linvel = Vec2::new(direction.x * speed, direction.y * speed);
-          }
-          None => (),
-        }
-      }
-      EnemyMovement::Random(direction) => {
-        let lower = direction - 0.3;
-        let upper = direction + 0.3;
+  for (entity, transform, mut reduction) in enemies.iter_mut() {
+    // auras don't stack, the strongest one in range counts
+    reduction.0 = bearers
+      .iter()
+      .filter(|(bearer, bearer_transform, shield)| {
+        *bearer != entity
+          && bearer_transform.translation.distance(transform.translation) <= shield.radius
+      })
+      .map(|(_, _, shield)| shield.reduction)
+      .fold(0.0, f32::max);
+  }
+}
 
-        let new_direction = rng.gen_range(lower..upper);
+// the only place enemies drop their reward, an enemy killed by several hits in one step still dies
+// once
+#[allow(clippy::too_many_arguments)]
+pub fn handle_enemy_death(
+  mut commands: Commands,
+  mut despawn_events: EventReader<DespawnEvent>,
+  enemies: Query<(
+    &Enemy,
+    &Transform,
+    Option<&SplitOnDeath>,
+    Option<&EnemyScaling>,
+  )>,
+  mut stats: ResMut<RunStats>,
+  textures: Res<TextureAtlasHandles>,
+  asset_server: Res<AssetServer>,
+  mut pools: ResMut<EntityPools>,
+  mut pooled: Query<&mut Pooled>,
+  mut rng: ResMut<GameRng>,
+) {
+  let mut dead = HashSet::new();
 
-        *movement = EnemyMovement::Random(new_direction);
+  for event in despawn_events.iter() {
+    if !dead.insert(event.entity) {
+      continue;
+    }
 
-        let x = rng.gen_range(0.0..75.0) * new_direction.cos();
-        let y = rng.gen_range(0.0..75.0) * new_direction.sin();
+    if let Ok((enemy, transform, split, scaling)) = enemies.get(event.entity) {
+      drop_coins(
+        &mut commands,
+        &asset_server,
+        &mut pools,
+        &mut pooled,
+        &mut *rng,
+        transform.translation,
+        enemy.reward,
+      );
+      stats.record_kill(enemy.kind);
 
-        velocity.linvel.x = x;
-        velocity.linvel.y = y;
+      if let Some(split) = split {
+        let scaling = scaling.copied().unwrap_or_default();
+        split_enemy(
+          &mut commands,
+          &textures,
+          &mut *rng,
+          transform,
+          split,
+          scaling,
+        );
       }
     }
   }
 }
 
-pub fn handle_charge(
-  mut query: Query<(&mut Charge, &mut Velocity, &Transform), With<Enemy>>,
-  players: Query<&Transform, With<Player>>,
-  time: Res<Time>,
+fn split_enemy(
+  commands: &mut Commands,
+  textures: &TextureAtlasHandles,
+  rng: &mut impl Rng,
+  transform: &Transform,
+  split: &SplitOnDeath,
+  scaling: EnemyScaling,
 ) {
-  for (mut charge, mut velocity, transform) in query.iter_mut() {
-    if charge.cooldown.tick(time.delta()).just_finished() {
-      charge.duration.reset();
-      charge.duration.unpause();
-      let speed = 300.0;
-      match closest_player(&transform, &players) {
-        Some(player) => {
-          let direction = (player - transform.translation).normalize_or_zero();
-          charge.direction = Vec2::new(direction.x * speed, direction.y * speed);
-        }
-        None => (),
-      }
-    }
-    if !charge.duration.paused() && !charge.duration.tick(time.delta()).finished() {
-      velocity.linvel = charge.direction;
+  for _ in 0..split.count {
+    let angle = rng.gen_range(0.0..std::f32::consts::TAU);
+    let distance = rng.gen_range(0.0..=split.spread);
+    let mut child_transform = transform.with_scale(transform.scale * split.scale);
+    child_transform.translation += Vec3::new(angle.cos(), angle.sin(), 0.0) * distance;
+
+    let child = spawn_split_child(
+      commands,
+      textures,
+      rng,
+      split.child,
+      child_transform,
+      split.health * scaling.health,
+    );
+    commands.entity(child).insert(scaling);
+
+    if split.depth > 0 {
+      commands.entity(child).insert(SplitOnDeath {
+        health: split.health * 0.5,
+        depth: split.depth - 1,
+        ..*split
+      });
     }
   }
 }
 
-pub fn handle_explosion(
-  mut commands: Commands,
-  enemies: Query<(Entity, &Enemy, &Health, &Transform), With<Explode>>,
-  mut state: ResMut<GameData>,
-  textures: Res<TextureAtlasHandles>,
-) {
-  for (entity, enemy, health, transform) in enemies.iter() {
-    if health.current_health <= 0.0 {
-      state.money += enemy.reward;
-
-      let mut rng = thread_rng();
-
-      for _ in 0..30 {
-        commands
-          .spawn((
-            OnGameScreen,
-            EnemyMovement::Random(rng.gen_range(0.0..2.0 * std::f32::consts::PI)),
-            Enemy { reward: 1 },
-            Health::new(100.0),
-            Charge {
-              cooldown: Timer::from_seconds(rng.gen_range(4.0..10.0), TimerMode::Repeating),
-              duration: {
-                let mut t = Timer::from_seconds(2.0, TimerMode::Once);
-                t.pause();
-                t
-              },
-              direction: Vec2::default(),
-            },
-            ActiveEvents::COLLISION_EVENTS,
-            CollisionGroups::new(Group::GROUP_3, Group::ALL),
-            SpriteSheetBundle {
-              texture_atlas: textures.boss_atlas_handle.clone(),
-              transform: transform.with_scale(Vec3::new(0.75, 0.75, 0.75)),
-              ..default()
-            },
-            AnimationTimer(Timer::from_seconds(0.2, TimerMode::Repeating)),
-          ))
-          .insert((
-            RigidBody::Dynamic,
-            Velocity {
-              linvel: Vec2::default(),
-              angvel: 0.0,
-            },
-            Damping {
-              linear_damping: 0.0,
-              angular_damping: 100000.0,
-            },
-            Collider::ball(12.),
-            Restitution::coefficient(0.7),
-          ));
-      }
+fn spawn_split_child(
+  commands: &mut Commands,
+  textures: &TextureAtlasHandles,
+  rng: &mut impl Rng,
+  child: SplitChild,
+  transform: Transform,
+  health: f32,
+) -> Entity {
+  match child {
+    SplitChild::Slime => spawn_enemy(
+      commands,
+      transform,
+      textures.atlas_handle.clone(),
+      health,
+      EnemyAi::new(AiParams {
+        idle_time: 0.3,
+        ..default()
+      }),
+      Enemy {
+        reward: 1,
+        kind: EnemyKind::Slime,
+      },
+      0.0,
+    ),
+    SplitChild::BossSpawn => commands
+      .spawn((
+        OnGameScreen,
+        // they wander off but every now and then charge at the closest player
+        EnemyAi::new(AiParams {
+          charge: Some(ChargeParams {
+            cooldown: rng.gen_range(4.0..10.0),
+            range: f32::INFINITY,
+            windup: 0.6,
+            duration: 2.0,
+            recover: 0.5,
+            speed: 300.0,
+          }),
+          ..default()
+        }),
+        Enemy {
+          reward: 1,
+          kind: EnemyKind::BossSpawn,
+        },
+        Health::new(health),
+        ActiveEvents::COLLISION_EVENTS,
+        CollisionGroups::new(Group::GROUP_3, Group::ALL),
+        SpriteSheetBundle {
+          texture_atlas: textures.boss_atlas_handle.clone(),
+          transform,
+          ..default()
+        },
+        AnimationTimer(Timer::from_seconds(0.2, TimerMode::Repeating)),
+      ))
+      .insert((
+        RigidBody::Dynamic,
+        Velocity {
+          linvel: Vec2::default(),
+          angvel: 0.0,
+        },
+        Damping {
+          linear_damping: 0.0,
+          angular_damping: 100000.0,
+        },
+        Collider::ball(12.),
+        Restitution::coefficient(0.7),
+        ExternalImpulse::default(),
+        ReadMassProperties::default(),
+        Stagger::new(0.3),
+        DamageReduction::default(),
+        SpeedMultiplier::default(),
+        Interpolated::new(transform.translation),
+      ))
+      .id(),
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::coins::Coin;
+  use crate::testing;
+  use crate::{DamageEvent, DamageSource};
+  use bevy::ecs::event::Events;
+
+  fn spawn_boss(mut commands: Commands, textures: Res<TextureAtlasHandles>) {
+    spawn_enemy_type(
+      &mut commands,
+      &textures,
+      &EnemySpawnerType::Boss,
+      Transform::default(),
+      EnemyScaling::default(),
+    );
+  }
+
+  fn count_kind(app: &mut App, kind: EnemyKind) -> usize {
+    let mut enemies = app.world.query::<&Enemy>();
+    enemies
+      .iter(&app.world)
+      .filter(|enemy| enemy.kind == kind)
+      .count()
+  }
+
+  fn dropped_money(app: &mut App) -> i32 {
+    let mut coins = app.world.query::<(&Coin, &Pooled)>();
+    coins
+      .iter(&app.world)
+      .filter(|(_, pooled)| pooled.active)
+      .map(|(coin, _)| coin.value)
+      .sum()
+  }
+
+  #[test]
+  fn boss_dies_once_however_often_it_is_killed() {
+    let mut app = testing::headless_app(1, spawn_boss);
+    let boss = app
+      .world
+      .query_filtered::<Entity, With<Boss>>()
+      .single(&app.world);
+    let split = *app.world.get::<SplitOnDeath>(boss).unwrap();
+    let reward = app.world.get::<Enemy>(boss).unwrap().reward;
+
+    // several lethal hits and despawns of the same boss all land in one step
+    let mut damage_events = app.world.resource_mut::<Events<DamageEvent>>();
+    for _ in 0..3 {
+      damage_events.send(DamageEvent {
+        entity: boss,
+        damage: 10000.0,
+        crit: false,
+        knockback: None,
+        source: DamageSource::Gun,
+      });
+    }
+    let mut despawn_events = app.world.resource_mut::<Events<DespawnEvent>>();
+    for _ in 0..2 {
+      despawn_events.send(DespawnEvent { entity: boss });
+    }
+
+    // the second step still sees last step's events, they must not count again
+    for _ in 0..2 {
+      testing::step(&mut app);
 
-      commands.entity(entity).despawn();
+      assert!(app.world.get_entity(boss).is_none());
+      assert_eq!(count_kind(&mut app, EnemyKind::Boss), 0);
+      assert_eq!(count_kind(&mut app, EnemyKind::BossSpawn), split.count);
+      assert_eq!(
+        app.world.resource::<RunStats>().kills.get(&EnemyKind::Boss),
+        Some(&1)
+      );
+      assert_eq!(dropped_money(&mut app), reward);
     }
   }
 }
@@ -0,0 +1,375 @@
+use crate::systems::rebuild_spatial_index;
+use crate::{DamageEvent, DespawnEvent, GameState};
+use bevy::ecs::event::Events;
+use bevy::ecs::schedule::ShouldRun;
+use bevy::prelude::*;
+use bevy::time::{FixedTimestep, FixedTimesteps};
+use bevy::transform::TransformSystem;
+use bevy_rapier2d::prelude::*;
+use rand::prelude::*;
+use std::time::Duration;
+
+// gameplay and physics advance in steps of this size no matter the frame rate
+pub const TIMESTEP: f64 = 1.0 / 60.0;
+const TIMESTEP_LABEL: &str = "gameplay_timestep";
+
+#[derive(StageLabel)]
+pub struct FixedUpdateStage;
+
+#[derive(StageLabel)]
+enum FixedStage {
+  Prepare,
+  SyncBackend,
+  Step,
+  Writeback,
+  Gameplay,
+}
+
+#[derive(SystemLabel)]
+enum FixedSystem {
+  SyncBackend,
+  Step,
+  Writeback,
+}
+
+// the Time of the simulation, gameplay systems read this instead of Time
+#[derive(Resource, Default)]
+pub struct GameTime {
+  delta: Duration,
+  elapsed: Duration,
+}
+
+impl GameTime {
+  pub fn delta(&self) -> Duration {
+    self.delta
+  }
+
+  pub fn delta_seconds(&self) -> f32 {
+    self.delta.as_secs_f32()
+  }
+
+  pub fn elapsed_seconds(&self) -> f32 {
+    self.elapsed.as_secs_f32()
+  }
+}
+
+// every random decision of the simulation comes from here, the same seed plays out the same way
+#[derive(Resource)]
+pub struct GameRng(StdRng);
+
+impl GameRng {
+  pub fn new(seed: u64) -> Self {
+    GameRng(StdRng::seed_from_u64(seed))
+  }
+}
+
+impl Default for GameRng {
+  fn default() -> Self {
+    GameRng::new(random())
+  }
+}
+
+impl RngCore for GameRng {
+  fn next_u32(&mut self) -> u32 {
+    self.0.next_u32()
+  }
+
+  fn next_u64(&mut self) -> u64 {
+    self.0.next_u64()
+  }
+
+  fn fill_bytes(&mut self, dest: &mut [u8]) {
+    self.0.fill_bytes(dest)
+  }
+
+  fn try_fill_bytes(&mut self, dest: &mut [u8]) -> Result<(), rand::Error> {
+    self.0.try_fill_bytes(dest)
+  }
+}
+
+// entities that are moved by the simulation and drawn in between steps
+#[derive(Component, Debug)]
+pub struct Interpolated {
+  previous: Vec3,
+  current: Vec3,
+}
+
+impl Interpolated {
+  pub fn new(translation: Vec3) -> Self {
+    Interpolated {
+      previous: translation,
+      current: translation,
+    }
+  }
+}
+
+pub struct FixedTimestepPlugin;
+
+impl Plugin for FixedTimestepPlugin {
+  fn build(&self, app: &mut App) {
+    let mut prepare = level_stage();
+    prepare
+      .add_system(advance_game_time)
+      .add_system(Events::<DamageEvent>::update_system)
+      .add_system(Events::<DespawnEvent>::update_system)
+      .add_system(restore_simulated_transforms);
+
+    // rapier's stages need the commands of the previous one applied, so each gets its own stage
+    let mut sync_backend = level_stage();
+    sync_backend.add_system_set(
+      RapierPhysicsPlugin::<NoUserData>::get_systems(PhysicsStages::SyncBackend)
+        .label(FixedSystem::SyncBackend),
+    );
+
+    let mut step = level_stage();
+    step.add_system_set(
+      RapierPhysicsPlugin::<NoUserData>::get_systems(PhysicsStages::StepSimulation)
+        .label(FixedSystem::Step),
+    );
+
+    let mut writeback = level_stage();
+    writeback
+      .add_system_set(
+        RapierPhysicsPlugin::<NoUserData>::get_systems(PhysicsStages::Writeback)
+          .label(FixedSystem::Writeback),
+      )
+      .add_system(record_simulated_transforms.after(FixedSystem::Writeback))
+      // gameplay queries positions after this step's movement
+      .add_system(rebuild_spatial_index.after(FixedSystem::Writeback));
+
+    // every step runs physics first so gameplay sees this step's collision events
+    let schedule = Schedule::default()
+      .with_run_criteria(FixedTimestep::step(TIMESTEP).with_label(TIMESTEP_LABEL))
+      .with_stage(FixedStage::Prepare, prepare)
+      .with_stage_after(FixedStage::Prepare, FixedStage::SyncBackend, sync_backend)
+      .with_stage_after(FixedStage::SyncBackend, FixedStage::Step, step)
+      .with_stage_after(FixedStage::Step, FixedStage::Writeback, writeback)
+      .with_stage_after(FixedStage::Writeback, FixedStage::Gameplay, level_stage());
+
+    app
+      .init_resource::<GameTime>()
+      .init_resource::<GameRng>()
+      // these events are updated once per step instead of once per frame, so no step misses them
+      .init_resource::<Events<DamageEvent>>()
+      .init_resource::<Events<DespawnEvent>>()
+      .insert_resource(RapierConfiguration {
+        gravity: Vec2::ZERO,
+        timestep_mode: TimestepMode::Fixed {
+          dt: TIMESTEP as f32,
+          substeps: 1,
+        },
+        ..default()
+      })
+      .add_stage_after(CoreStage::Update, FixedUpdateStage, schedule)
+      .add_system_set_to_stage(
+        CoreStage::Last,
+        RapierPhysicsPlugin::<NoUserData>::get_systems(PhysicsStages::DetectDespawn),
+      )
+      .add_system_to_stage(
+        CoreStage::PostUpdate,
+        interpolate_transforms.after(TransformSystem::TransformPropagate),
+      );
+  }
+}
+
+// gameplay systems that run once per step, levels use this instead of add_system_set
+pub fn add_gameplay_systems(app: &mut App, system_sets: impl IntoIterator<Item = SystemSet>) {
+  app
+    .schedule
+    .stage(FixedUpdateStage, |schedule: &mut Schedule| {
+      for system_set in system_sets {
+        schedule.add_system_set_to_stage(FixedStage::Gameplay, system_set);
+      }
+      schedule
+    });
+}
+
+// the gameplay systems of a level, SystemSet::on_update can't be used here as it needs the state
+// driver in the same stage
+pub fn on_step(state: GameState) -> SystemSet {
+  SystemSet::new().with_run_criteria(move |current: Res<State<GameState>>| {
+    if *current.current() == state {
+      ShouldRun::Yes
+    } else {
+      ShouldRun::No
+    }
+  })
+}
+
+// steps keep being consumed outside of levels, they just don't simulate anything
+fn level_stage() -> SystemStage {
+  SystemStage::parallel().with_run_criteria(in_level)
+}
+
+fn in_level(state: Res<State<GameState>>) -> ShouldRun {
+  if state.current().is_level() {
+    ShouldRun::Yes
+  } else {
+    ShouldRun::No
+  }
+}
+
+fn advance_game_time(mut time: ResMut<GameTime>) {
+  let delta = Duration::from_secs_f64(TIMESTEP);
+  time.delta = delta;
+  time.elapsed += delta;
+}
+
+// undo the interpolation of the last frame, physics has to see where things really are
+fn restore_simulated_transforms(
+  mut query: Query<(&Transform, &mut GlobalTransform), (With<Interpolated>, Without<Parent>)>,
+) {
+  for (transform, mut global_transform) in query.iter_mut() {
+    *global_transform = GlobalTransform::from(*transform);
+  }
+}
+
+fn record_simulated_transforms(mut query: Query<(&Transform, &mut Interpolated)>) {
+  for (transform, mut interpolated) in query.iter_mut() {
+    interpolated.previous = interpolated.current;
+    interpolated.current = transform.translation;
+  }
+}
+
+// draw entities between their last two simulated positions, only the GlobalTransform is touched
+fn interpolate_transforms(
+  timesteps: Res<FixedTimesteps>,
+  mut roots: Query<
+    (
+      &Transform,
+      &Interpolated,
+      &mut GlobalTransform,
+      Option<&Children>,
+    ),
+    Without<Parent>,
+  >,
+  mut children: Query<(&Transform, &mut GlobalTransform), (With<Parent>, Without<Interpolated>)>,
+) {
+  let alpha = timesteps
+    .get(TIMESTEP_LABEL)
+    .map_or(1.0, |state| state.overstep_percentage() as f32)
+    .min(1.0);
+
+  for (transform, interpolated, mut global_transform, entity_children) in roots.iter_mut() {
+    let offset = interpolated.previous.lerp(interpolated.current, alpha) - interpolated.current;
+    *global_transform = GlobalTransform::from(Transform {
+      translation: transform.translation + offset,
+      ..*transform
+    });
+
+    // icons and range indicators follow their parent
+    for child in entity_children.into_iter().flatten() {
+      if let Ok((child_transform, mut child_global_transform)) = children.get_mut(*child) {
+        *child_global_transform = global_transform.mul_transform(*child_transform);
+      }
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::components::Health;
+  use crate::enemy::{Enemy, EnemySpawnerType, SpawnPattern};
+  use crate::map;
+  use crate::player::{create_player, Player};
+  use crate::testing;
+  use crate::waves::{LevelWaves, SpawnerGroup, Wave, WaveTrigger};
+  use crate::{Action, GameData};
+  use leafwing_input_manager::prelude::*;
+
+  const STEPS: usize = 600;
+
+  #[derive(PartialEq, Debug)]
+  struct Snapshot {
+    money: i32,
+    players: Vec<(Vec3, f32)>,
+    enemies: Vec<(Vec3, f32)>,
+  }
+
+  #[derive(Resource, Default)]
+  struct Snapshots(Vec<Snapshot>);
+
+  // a player with its guns against a wave of wandering slimes
+  fn setup(mut commands: Commands, asset_server: Res<AssetServer>, data: Res<GameData>) {
+    map::create_map_boundary(
+      &mut commands,
+      map::ArenaShape::Rectangle {
+        half_size: Vec2::splat(600.0),
+      },
+    );
+    create_player(
+      &mut commands,
+      Player::One,
+      Handle::default(),
+      InputManagerBundle::<Action> {
+        action_state: ActionState::default(),
+        input_map: InputMap::default(),
+      },
+      &asset_server,
+      &data,
+    );
+    commands.insert_resource(LevelWaves::new(vec![Wave {
+      name: "Wave 1".into(),
+      groups: vec![SpawnerGroup::new(
+        Vec3::new(150.0, 0.0, 0.0),
+        EnemySpawnerType::Normal,
+        12,
+        0.5,
+      )
+      .with_pattern(SpawnPattern::Burst {
+        count: 3,
+        spread: 60.0,
+      })],
+      trigger: WaveTrigger::Cleared,
+      intermission: None,
+    }]));
+  }
+
+  fn state((transform, health): (&Transform, &Health)) -> (Vec3, f32) {
+    (transform.translation, health.current_health)
+  }
+
+  fn record(
+    players: Query<(&Transform, &Health), With<Player>>,
+    enemies: Query<(&Transform, &Health), With<Enemy>>,
+    data: Res<GameData>,
+    mut snapshots: ResMut<Snapshots>,
+  ) {
+    snapshots.0.push(Snapshot {
+      money: data.money,
+      players: players.iter().map(state).collect(),
+      enemies: enemies.iter().map(state).collect(),
+    });
+  }
+
+  fn run(frame_rate: f64) -> Vec<Snapshot> {
+    let mut app = testing::headless_app(7, setup);
+    app.init_resource::<Snapshots>();
+    // once the step is over, so nothing of it is missed
+    app
+      .schedule
+      .stage(FixedUpdateStage, |schedule: &mut Schedule| {
+        schedule.add_stage_after(FixedStage::Gameplay, "record", SystemStage::single(record))
+      });
+
+    while app.world.resource::<Snapshots>().0.len() < STEPS {
+      testing::run_frame(&mut app, Duration::from_secs_f64(1.0 / frame_rate));
+    }
+
+    let mut snapshots = app.world.remove_resource::<Snapshots>().unwrap().0;
+    snapshots.truncate(STEPS);
+    snapshots
+  }
+
+  #[test]
+  fn same_run_at_any_frame_rate() {
+    let slow = run(30.0);
+    let fast = run(144.0);
+
+    assert!(slow.iter().any(|snapshot| !snapshot.enemies.is_empty()));
+    for (step, (slow, fast)) in slow.iter().zip(fast.iter()).enumerate() {
+      assert_eq!(slow, fast, "runs differ at step {}", step);
+    }
+  }
+}
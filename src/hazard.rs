@@ -0,0 +1,259 @@
+use crate::components::*;
+use crate::enemy::Enemy;
+use crate::fixed::{GameTime, Interpolated};
+use crate::player::Player;
+use crate::{DamageEvent, DamageSource};
+use bevy::prelude::*;
+use bevy::utils::HashMap;
+use bevy_prototype_lyon::prelude::FillMode;
+use bevy_prototype_lyon::prelude::*;
+use bevy_rapier2d::prelude::*;
+
+#[derive(Clone, Copy, Debug)]
+pub enum HazardEffect {
+  // damage per second
+  Damage(f32),
+  // health per second, never above max health
+  Heal(f32),
+  // multiplies the speed of everything inside, below 1.0 slows and above speeds up
+  Speed(f32),
+}
+
+impl HazardEffect {
+  fn color(&self) -> Color {
+    match self {
+      HazardEffect::Damage(_) => Color::rgb(1.0, 0.4, 0.0),
+      HazardEffect::Heal(_) => Color::rgb(0.2, 0.9, 0.3),
+      HazardEffect::Speed(multiplier) if *multiplier < 1.0 => Color::rgb(0.2, 0.4, 1.0),
+      HazardEffect::Speed(_) => Color::rgb(1.0, 0.9, 0.2),
+    }
+  }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum HazardTarget {
+  Players,
+  Enemies,
+  Both,
+}
+
+#[derive(Clone, Copy, Debug)]
+pub enum HazardMotion {
+  Still,
+  // back and forth between the spawn position and `to`, one round trip per period
+  Patrol { to: Vec2, period: f32 },
+  // circles around the spawn position
+  Orbit { radius: f32, period: f32 },
+}
+
+// a zone as placed by the level data
+#[derive(Clone, Debug)]
+pub struct HazardZone {
+  pub position: Vec2,
+  pub radius: f32,
+  pub effect: HazardEffect,
+  pub target: HazardTarget,
+  pub motion: HazardMotion,
+  // seconds on and off, zones without a pulse are always on
+  pub pulse: Option<(f32, f32)>,
+}
+
+impl HazardZone {
+  pub fn new(position: Vec2, radius: f32, effect: HazardEffect, target: HazardTarget) -> Self {
+    HazardZone {
+      position,
+      radius,
+      effect,
+      target,
+      motion: HazardMotion::Still,
+      pulse: None,
+    }
+  }
+
+  pub fn with_motion(mut self, motion: HazardMotion) -> Self {
+    self.motion = motion;
+    self
+  }
+
+  pub fn with_pulse(mut self, on: f32, off: f32) -> Self {
+    self.pulse = Some((on, off));
+    self
+  }
+}
+
+#[derive(Component, Debug)]
+pub struct Hazard {
+  pub effect: HazardEffect,
+  pub target: HazardTarget,
+  pub motion: HazardMotion,
+  pub pulse: Option<(f32, f32)>,
+  origin: Vec2,
+  elapsed: f32,
+}
+
+impl Hazard {
+  pub fn new(effect: HazardEffect, target: HazardTarget) -> Self {
+    Hazard {
+      effect,
+      target,
+      motion: HazardMotion::Still,
+      pulse: None,
+      origin: Vec2::ZERO,
+      elapsed: 0.0,
+    }
+  }
+
+  pub fn is_active(&self) -> bool {
+    self
+      .pulse
+      .map_or(true, |(on, off)| self.elapsed % (on + off) < on)
+  }
+
+  // enemies path around zones that stay put and hurt them, moving zones cross every path sooner or
+  // later so those are simply walked through
+  pub fn blocks_enemies(&self) -> bool {
+    matches!(self.effect, HazardEffect::Damage(_))
+      && matches!(self.motion, HazardMotion::Still)
+      && self.affects(false)
+  }
+
+  fn affects(&self, player: bool) -> bool {
+    match self.target {
+      HazardTarget::Players => player,
+      HazardTarget::Enemies => !player,
+      HazardTarget::Both => true,
+    }
+  }
+
+  fn position(&self) -> Vec2 {
+    match self.motion {
+      HazardMotion::Still => self.origin,
+      HazardMotion::Patrol { to, period } => {
+        // 0 at the spawn position, 1 at `to`
+        let progress = 1.0 - (self.elapsed / period * std::f32::consts::TAU).cos();
+        self.origin.lerp(to, progress / 2.0)
+      }
+      HazardMotion::Orbit { radius, period } => {
+        let angle = self.elapsed / period * std::f32::consts::TAU;
+        self.origin + Vec2::new(angle.cos(), angle.sin()) * radius
+      }
+    }
+  }
+}
+
+pub fn create_hazards(commands: &mut Commands, zones: &[HazardZone]) {
+  for zone in zones {
+    let transform = Transform::from_translation(zone.position.extend(0.02));
+    let shape = shapes::Circle {
+      radius: zone.radius,
+      center: Vec2::ZERO,
+    };
+
+    commands.spawn((
+      OnGameScreen,
+      Hazard {
+        motion: zone.motion,
+        pulse: zone.pulse,
+        origin: zone.position,
+        ..Hazard::new(zone.effect, zone.target)
+      },
+      GeometryBuilder::build_as(&shape, hazard_draw_mode(zone.effect, true), transform),
+      Interpolated::new(transform.translation),
+      Collider::ball(zone.radius),
+      Sensor,
+      CollidingEntities::default(),
+      ActiveEvents::COLLISION_EVENTS,
+      RigidBody::KinematicPositionBased,
+      // players and enemies
+      CollisionGroups::new(Group::GROUP_8, Group::GROUP_1.union(Group::GROUP_3)),
+    ));
+  }
+}
+
+fn hazard_draw_mode(effect: HazardEffect, active: bool) -> DrawMode {
+  let mut fill = effect.color();
+  fill.set_a(if active { 0.3 } else { 0.08 });
+  let mut outline = effect.color();
+  outline.set_a(if active { 0.8 } else { 0.3 });
+
+  DrawMode::Outlined {
+    fill_mode: FillMode::color(fill),
+    outline_mode: StrokeMode::new(outline, 2.0),
+  }
+}
+
+pub fn update_hazards(
+  mut hazards: Query<(&mut Hazard, &mut Transform, Option<&mut DrawMode>)>,
+  time: Res<GameTime>,
+) {
+  for (mut hazard, mut transform, draw_mode) in hazards.iter_mut() {
+    let was_active = hazard.is_active();
+    hazard.elapsed += time.delta_seconds();
+
+    if !matches!(hazard.motion, HazardMotion::Still) {
+      let position = hazard.position();
+      transform.translation.x = position.x;
+      transform.translation.y = position.y;
+    }
+
+    if let Some(mut draw_mode) = draw_mode {
+      if hazard.is_active() != was_active {
+        *draw_mode = hazard_draw_mode(hazard.effect, hazard.is_active());
+      }
+    }
+  }
+}
+
+pub fn apply_hazards(
+  hazards: Query<(&Hazard, &CollidingEntities, Option<&Killzone>)>,
+  mut targets: Query<
+    (Entity, &mut Health, &mut SpeedMultiplier, Option<&Player>),
+    Or<(With<Player>, With<Enemy>)>,
+  >,
+  mut damage_event: EventWriter<DamageEvent>,
+  time: Res<GameTime>,
+) {
+  let mut speed: HashMap<Entity, f32> = HashMap::default();
+
+  for (hazard, colliding, killzone) in hazards.iter() {
+    if !hazard.is_active() {
+      continue;
+    }
+
+    for entity in colliding.iter() {
+      let (_, mut health, _, player) = match targets.get_mut(entity) {
+        Ok(target) => target,
+        Err(_) => continue,
+      };
+      if !hazard.affects(player.is_some()) {
+        continue;
+      }
+
+      match hazard.effect {
+        HazardEffect::Damage(per_second) => damage_event.send(DamageEvent {
+          entity,
+          damage: per_second * time.delta_seconds(),
+          crit: false,
+          knockback: None,
+          source: if killzone.is_some() {
+            DamageSource::Killzone
+          } else {
+            DamageSource::Hazard
+          },
+        }),
+        HazardEffect::Heal(per_second) => {
+          if health.current_health > 0.0 {
+            health.current_health =
+              (health.current_health + per_second * time.delta_seconds()).min(health.max_health);
+          }
+        }
+        HazardEffect::Speed(multiplier) => *speed.entry(entity).or_insert(1.0) *= multiplier,
+      }
+    }
+  }
+
+  // entities that left every speed zone go back to normal
+  for (entity, _, mut multiplier, _) in targets.iter_mut() {
+    multiplier.0 = speed.get(&entity).copied().unwrap_or(1.0);
+  }
+}
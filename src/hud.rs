@@ -0,0 +1,472 @@
+use crate::ai::{AiState, EnemyAi};
+use crate::components::*;
+use crate::enemy::*;
+use crate::player::Player;
+use crate::waves::LevelWaves;
+use crate::weapons::laser::LaserGun;
+use crate::weapons::lightning::LightningGun;
+use crate::GameData;
+use bevy::prelude::*;
+
+const TEXT_COLOR: Color = Color::rgb(0.9, 0.9, 0.9);
+const BAR_BACKGROUND: Color = Color::rgba(0.0, 0.0, 0.0, 0.5);
+const HEALTH_COLOR: Color = Color::rgb(0.8, 0.15, 0.15);
+const COOLDOWN_COLOR: Color = Color::rgb(0.4, 0.4, 0.6);
+const COOLDOWN_READY_COLOR: Color = Color::CYAN;
+const BOSS_HEALTH_COLOR: Color = Color::rgb(0.55, 0.1, 0.6);
+const BOSS_CHARGE_COLOR: Color = Color::rgb(0.9, 0.6, 0.1);
+const BOSS_CHARGE_WARNING_COLOR: Color = Color::rgb(1.0, 0.1, 0.1);
+const PLAYER_PANEL_WIDTH: f32 = 240.0;
+
+#[derive(Component)]
+pub struct HealthBar(pub Player);
+
+#[derive(Component)]
+pub struct CooldownBar(pub Player);
+
+#[derive(Component)]
+pub struct PlayerSlot(pub Player);
+
+#[derive(Component)]
+pub struct MoneyText;
+
+#[derive(Component)]
+pub struct ProgressText;
+
+#[derive(Component)]
+pub struct BossBar;
+
+#[derive(Component)]
+pub struct BossHealthBar;
+
+#[derive(Component)]
+pub struct BossChargeBar;
+
+#[derive(Component)]
+pub struct Banner;
+
+#[derive(Component)]
+pub struct BannerText;
+
+fn spawn_bar(
+  parent: &mut ChildBuilder,
+  width: f32,
+  height: f32,
+  color: Color,
+  marker: impl Component,
+) {
+  parent
+    .spawn(NodeBundle {
+      style: Style {
+        size: Size::new(Val::Px(width), Val::Px(height)),
+        margin: UiRect::all(Val::Px(4.0)),
+        ..default()
+      },
+      background_color: BAR_BACKGROUND.into(),
+      ..default()
+    })
+    .with_children(|parent| {
+      parent.spawn((
+        marker,
+        NodeBundle {
+          style: Style {
+            size: Size::new(Val::Percent(100.0), Val::Percent(100.0)),
+            ..default()
+          },
+          background_color: color.into(),
+          ..default()
+        },
+      ));
+    });
+}
+
+fn spawn_player_panel(
+  parent: &mut ChildBuilder,
+  player: Player,
+  text_style: TextStyle,
+  icon: Handle<Image>,
+) {
+  parent
+    .spawn(NodeBundle {
+      style: Style {
+        flex_direction: FlexDirection::Column,
+        align_items: match player {
+          Player::One => AlignItems::FlexStart,
+          Player::Two => AlignItems::FlexEnd,
+        },
+        ..default()
+      },
+      ..default()
+    })
+    .with_children(|parent| {
+      parent.spawn(TextBundle::from_section(
+        match player {
+          Player::One => "Player 1",
+          Player::Two => "Player 2",
+        },
+        text_style,
+      ));
+
+      spawn_bar(parent, 200.0, 14.0, HEALTH_COLOR, HealthBar(player));
+
+      parent
+        .spawn(NodeBundle {
+          style: Style {
+            flex_direction: FlexDirection::Row,
+            align_items: AlignItems::Center,
+            ..default()
+          },
+          ..default()
+        })
+        .with_children(|parent| {
+          parent.spawn(ImageBundle {
+            style: Style {
+              size: Size::new(Val::Px(20.0), Val::Px(20.0)),
+              ..default()
+            },
+            image: UiImage::from(icon),
+            ..default()
+          });
+
+          spawn_bar(parent, 200.0, 8.0, COOLDOWN_COLOR, CooldownBar(player));
+        });
+    });
+}
+
+// empty slots keep the center column in place when a player is missing
+fn spawn_player_slot(parent: &mut ChildBuilder, player: Player) {
+  parent.spawn((
+    PlayerSlot(player),
+    NodeBundle {
+      style: Style {
+        flex_direction: FlexDirection::Column,
+        align_items: match player {
+          Player::One => AlignItems::FlexStart,
+          Player::Two => AlignItems::FlexEnd,
+        },
+        size: Size::new(Val::Px(PLAYER_PANEL_WIDTH), Val::Auto),
+        ..default()
+      },
+      ..default()
+    },
+  ));
+}
+
+// panels are only added for players that are actually in the run
+pub fn spawn_player_panels(
+  mut commands: Commands,
+  asset_server: Res<AssetServer>,
+  players: Query<&Player, Added<Player>>,
+  slots: Query<(Entity, &PlayerSlot)>,
+) {
+  for player in players.iter() {
+    for (entity, slot) in slots.iter() {
+      if slot.0 != *player {
+        continue;
+      }
+
+      let text_style = TextStyle {
+        font: asset_server.load("font.ttf"),
+        font_size: 24.0,
+        color: TEXT_COLOR,
+      };
+      let icon = asset_server.load(match player {
+        Player::One => "lightning_icon.png",
+        Player::Two => "laser_icon.png",
+      });
+
+      commands.entity(entity).with_children(|parent| {
+        spawn_player_panel(parent, *player, text_style, icon);
+      });
+    }
+  }
+}
+
+pub fn setup_hud(mut commands: Commands, asset_server: Res<AssetServer>) {
+  let font = asset_server.load("font.ttf");
+  let text_style = TextStyle {
+    font: font.clone(),
+    font_size: 24.0,
+    color: TEXT_COLOR,
+  };
+
+  commands
+    .spawn((
+      OnGameScreen,
+      NodeBundle {
+        style: Style {
+          position_type: PositionType::Absolute,
+          justify_content: JustifyContent::SpaceBetween,
+          align_items: AlignItems::FlexStart,
+          flex_direction: FlexDirection::Row,
+          size: Size::new(Val::Percent(100.0), Val::Percent(100.0)),
+          padding: UiRect::all(Val::Px(10.0)),
+          ..default()
+        },
+        ..default()
+      },
+    ))
+    .with_children(|parent| {
+      spawn_player_slot(parent, Player::One);
+
+      parent
+        .spawn(NodeBundle {
+          style: Style {
+            flex_direction: FlexDirection::Column,
+            align_items: AlignItems::Center,
+            ..default()
+          },
+          ..default()
+        })
+        .with_children(|parent| {
+          parent.spawn((MoneyText, TextBundle::from_section("", text_style.clone())));
+          parent.spawn((
+            ProgressText,
+            TextBundle::from_section("", text_style.clone()),
+          ));
+        });
+
+      spawn_player_slot(parent, Player::Two);
+    });
+}
+
+pub fn update_health_bars(
+  players: Query<(&Player, &Health)>,
+  mut bars: Query<(&mut Style, &HealthBar)>,
+) {
+  for (mut style, bar) in bars.iter_mut() {
+    let percent = players
+      .iter()
+      .find(|(player, _)| **player == bar.0)
+      .map(|(_, health)| (health.current_health / health.max_health).clamp(0.0, 1.0))
+      .unwrap_or(0.0);
+
+    style.size.width = Val::Percent(percent * 100.0);
+  }
+}
+
+pub fn update_cooldown_bars(
+  players: Query<&Player>,
+  lightning_guns: Query<(&Parent, &LightningGun)>,
+  laser_guns: Query<(&Parent, &LaserGun)>,
+  mut bars: Query<(&mut Style, &mut BackgroundColor, &CooldownBar)>,
+) {
+  let cooldowns = lightning_guns
+    .iter()
+    .map(|(parent, gun)| (parent.get(), gun.cooldown.percent()))
+    .chain(
+      laser_guns
+        .iter()
+        .map(|(parent, gun)| (parent.get(), gun.cooldown.percent())),
+    );
+
+  let mut percents: Vec<(Player, f32)> = Vec::new();
+  for (entity, percent) in cooldowns {
+    if let Ok(player) = players.get(entity) {
+      percents.push((*player, percent));
+    }
+  }
+
+  for (mut style, mut color, bar) in bars.iter_mut() {
+    let percent = percents
+      .iter()
+      .find(|(player, _)| *player == bar.0)
+      .map(|(_, percent)| *percent)
+      .unwrap_or(0.0);
+
+    style.size.width = Val::Percent(percent * 100.0);
+    *color = if percent >= 1.0 {
+      COOLDOWN_READY_COLOR.into()
+    } else {
+      COOLDOWN_COLOR.into()
+    };
+  }
+}
+
+pub fn update_money_text(data: Res<GameData>, mut texts: Query<&mut Text, With<MoneyText>>) {
+  for mut text in texts.iter_mut() {
+    text.sections[0].value = format!("${}", data.money);
+  }
+}
+
+pub fn update_progress_text(
+  spawners: Query<&EnemySpawner>,
+  enemies: Query<&Enemy>,
+  waves: Res<LevelWaves>,
+  mut texts: Query<&mut Text, With<ProgressText>>,
+) {
+  let unspawned: usize = spawners
+    .iter()
+    .map(|spawner| spawner.spawn_limit.saturating_sub(spawner.spawn_count))
+    .sum();
+  let remaining = unspawned + enemies.iter().count();
+
+  let wave = if waves.endless {
+    format!("Wave {}", waves.started)
+  } else {
+    format!("Wave {}/{}", waves.started, waves.waves.len())
+  };
+
+  for mut text in texts.iter_mut() {
+    text.sections[0].value = format!("{}  Enemies left: {}", wave, remaining);
+  }
+}
+
+pub fn spawn_boss_bar(
+  mut commands: Commands,
+  asset_server: Res<AssetServer>,
+  bosses: Query<&Boss, Added<Boss>>,
+  bars: Query<(), With<BossBar>>,
+) {
+  // one bar covers every boss alive, a second boss joining the fight doesn't get another
+  if !bars.is_empty() {
+    return;
+  }
+
+  if let Some(boss) = bosses.iter().next() {
+    let font = asset_server.load("font.ttf");
+
+    commands
+      .spawn((
+        OnGameScreen,
+        BossBar,
+        NodeBundle {
+          style: Style {
+            position_type: PositionType::Absolute,
+            justify_content: JustifyContent::FlexEnd,
+            align_items: AlignItems::Center,
+            flex_direction: FlexDirection::Column,
+            size: Size::new(Val::Percent(100.0), Val::Percent(100.0)),
+            padding: UiRect::all(Val::Px(20.0)),
+            ..default()
+          },
+          ..default()
+        },
+      ))
+      .with_children(|parent| {
+        parent.spawn(TextBundle::from_section(
+          boss.name.clone(),
+          TextStyle {
+            font: font.clone(),
+            font_size: 28.0,
+            color: TEXT_COLOR,
+          },
+        ));
+        spawn_bar(parent, 600.0, 18.0, BOSS_HEALTH_COLOR, BossHealthBar);
+        spawn_bar(parent, 600.0, 6.0, BOSS_CHARGE_COLOR, BossChargeBar);
+      });
+
+    spawn_banner(
+      &mut commands,
+      font.clone(),
+      format!("{} approaches!", boss.name),
+      BOSS_CHARGE_WARNING_COLOR,
+      JustifyContent::Center,
+    );
+  }
+}
+
+// large text that fades out, used for announcements during a level
+pub fn spawn_banner(
+  commands: &mut Commands,
+  font: Handle<Font>,
+  text: String,
+  color: Color,
+  justify_content: JustifyContent,
+) {
+  commands
+    .spawn((
+      OnGameScreen,
+      Banner,
+      ExpirationTimer(Timer::from_seconds(3.0, TimerMode::Once)),
+      NodeBundle {
+        style: Style {
+          position_type: PositionType::Absolute,
+          flex_direction: FlexDirection::Column,
+          justify_content,
+          align_items: AlignItems::Center,
+          size: Size::new(Val::Percent(100.0), Val::Percent(100.0)),
+          padding: UiRect::all(Val::Px(80.0)),
+          ..default()
+        },
+        ..default()
+      },
+    ))
+    .with_children(|parent| {
+      parent.spawn((
+        BannerText,
+        TextBundle::from_section(
+          text,
+          TextStyle {
+            font,
+            font_size: 64.0,
+            color,
+          },
+        ),
+      ));
+    });
+}
+
+pub fn update_boss_bar(
+  mut commands: Commands,
+  bosses: Query<(&Health, Option<&EnemyAi>), With<Boss>>,
+  bars: Query<Entity, With<BossBar>>,
+  mut health_bars: Query<&mut Style, (With<BossHealthBar>, Without<BossChargeBar>)>,
+  mut charge_bars: Query<(&mut Style, &mut BackgroundColor), With<BossChargeBar>>,
+  time: Res<Time>,
+) {
+  // the bar goes away once the last boss is gone, e.g. when it splits on death
+  if bosses.is_empty() {
+    for entity in bars.iter() {
+      commands.entity(entity).despawn_recursive();
+    }
+    return;
+  }
+
+  let health: f32 = bosses
+    .iter()
+    .map(|(health, _)| health.current_health.max(0.0))
+    .sum();
+  let max_health: f32 = bosses.iter().map(|(health, _)| health.max_health).sum();
+
+  for mut style in health_bars.iter_mut() {
+    style.size.width = Val::Percent(health / max_health * 100.0);
+  }
+
+  if let Some(ai) = bosses
+    .iter()
+    .find_map(|(_, ai)| ai)
+    .filter(|ai| ai.params.charge.is_some())
+  {
+    let charging = matches!(ai.state, AiState::Windup | AiState::Charge);
+    let telegraph = charging || ai.charge_cooldown.remaining_secs() < 1.5;
+    let blink = (time.elapsed_seconds() * 8.0).sin() > 0.0;
+
+    for (mut style, mut color) in charge_bars.iter_mut() {
+      style.size.width = if charging {
+        Val::Percent(100.0)
+      } else {
+        Val::Percent(ai.charge_cooldown.percent() * 100.0)
+      };
+      *color = if telegraph && blink {
+        BOSS_CHARGE_WARNING_COLOR.into()
+      } else {
+        BOSS_CHARGE_COLOR.into()
+      };
+    }
+  }
+}
+
+pub fn fade_banners(
+  mut texts: Query<(&Parent, &mut Text), With<BannerText>>,
+  banners: Query<&ExpirationTimer, With<Banner>>,
+) {
+  for (parent, mut text) in texts.iter_mut() {
+    if let Ok(timer) = banners.get(parent.get()) {
+      let alpha = timer.0.percent_left();
+      for section in text.sections.iter_mut() {
+        section.style.color.set_a(alpha);
+      }
+    }
+  }
+}
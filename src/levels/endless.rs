@@ -0,0 +1,282 @@
+use crate::components::*;
+use crate::enemy::*;
+use crate::fixed::{self, GameRng};
+use crate::hazard::{self, HazardEffect, HazardMotion, HazardTarget, HazardZone};
+use crate::hud;
+use crate::levels;
+use crate::map;
+use crate::player::*;
+use crate::props::{self, Loot, PowerUpKind, PropKind, PropPlacement};
+use crate::stats::{self, RunStats};
+use crate::systems;
+use crate::waves::*;
+use crate::{despawn_screen, Action, GameData, GameState, TextureAtlasHandles};
+
+use bevy::prelude::*;
+use leafwing_input_manager::prelude::*;
+use rand::prelude::*;
+
+pub struct EndlessPlugin;
+
+impl Plugin for EndlessPlugin {
+  fn build(&self, app: &mut App) {
+    app
+      .add_system_set(
+        SystemSet::on_enter(GameState::Endless)
+          .with_system(init)
+          .with_system(hud::setup_hud)
+          .with_system(stats::reset_run_stats),
+      )
+      .add_system_set(levels::frame_systems(GameState::Endless).with_system(end_condition.at_end()))
+      // When exiting the state, despawn everything that was spawned for this screen
+      .add_system_set(
+        SystemSet::on_exit(GameState::Endless)
+          .with_system(despawn_screen::<OnGameScreen>)
+          .with_system(systems::save_game),
+      );
+
+    let mut gameplay = levels::gameplay_systems(GameState::Endless);
+    gameplay.push(
+      fixed::on_step(GameState::Endless)
+        .label(levels::GameplaySystem::Input)
+        .with_system(queue_waves.after(player_movement)),
+    );
+    fixed::add_gameplay_systems(app, gameplay);
+  }
+}
+
+#[derive(Resource)]
+pub struct EndlessRun {
+  pub players: usize,
+}
+
+impl Default for EndlessRun {
+  fn default() -> Self {
+    EndlessRun { players: 2 }
+  }
+}
+
+const SPAWN_POINTS: [Vec3; 4] = [
+  Vec3::new(400., 400., 0.0),
+  Vec3::new(-400., 400., 0.0),
+  Vec3::new(400., -400., 0.0),
+  Vec3::new(-400., -400., 0.0),
+];
+
+fn init(
+  mut commands: Commands,
+  asset_server: Res<AssetServer>,
+  textures: Res<TextureAtlasHandles>,
+  state: Res<GameData>,
+  run: Res<EndlessRun>,
+) {
+  map::create_map_boundary(
+    &mut commands,
+    map::ArenaShape::Rectangle {
+      half_size: Vec2::splat(600.0),
+    },
+  );
+  hazard::create_hazards(
+    &mut commands,
+    &[
+      HazardZone::new(
+        Vec2::new(-400.0, -150.0),
+        50.0,
+        HazardEffect::Damage(15.0),
+        HazardTarget::Both,
+      )
+      .with_motion(HazardMotion::Patrol {
+        to: Vec2::new(400.0, -150.0),
+        period: 16.0,
+      })
+      .with_pulse(5.0, 3.0),
+      HazardZone::new(
+        Vec2::new(0.0, 150.0),
+        70.0,
+        HazardEffect::Speed(0.6),
+        HazardTarget::Enemies,
+      ),
+    ],
+  );
+  map::create_scenery(
+    &mut commands,
+    &[
+      map::Scenery::Pillar {
+        position: Vec2::new(250.0, 0.0),
+        radius: 25.0,
+      },
+      map::Scenery::Pillar {
+        position: Vec2::new(-250.0, 0.0),
+        radius: 25.0,
+      },
+      map::Scenery::Rock {
+        position: Vec2::new(0.0, 300.0),
+        radius: 35.0,
+      },
+      map::Scenery::Rock {
+        position: Vec2::new(0.0, -300.0),
+        radius: 35.0,
+      },
+    ],
+  );
+  props::create_props(
+    &mut commands,
+    &textures,
+    &[
+      PropPlacement::new(PropKind::Crate, Vec2::new(-150.0, 300.0)).with_loot(Loot::Money(20)),
+      PropPlacement::new(PropKind::Crate, Vec2::new(150.0, -300.0)).with_loot(Loot::Money(20)),
+      PropPlacement::new(PropKind::Barrel, Vec2::new(300.0, 250.0))
+        .with_loot(Loot::PowerUp(PowerUpKind::RapidFire)),
+      PropPlacement::new(PropKind::Barrel, Vec2::new(-300.0, -250.0))
+        .with_loot(Loot::PowerUp(PowerUpKind::Haste)),
+    ],
+  );
+
+  commands.insert_resource(LevelWaves::endless());
+
+  create_player(
+    &mut commands,
+    Player::One,
+    textures.player_atlas_handle.clone(),
+    InputManagerBundle::<Action> {
+      action_state: ActionState::default(),
+      input_map: InputMap::default()
+        .insert(VirtualDPad::arrow_keys(), Action::Move)
+        .insert(KeyCode::Space, Action::Attack)
+        .build(),
+    },
+    &asset_server,
+    &state,
+  );
+
+  if run.players >= 2 {
+    create_player(
+      &mut commands,
+      Player::Two,
+      textures.player_atlas_handle.clone(),
+      InputManagerBundle::<Action> {
+        action_state: ActionState::default(),
+        input_map: InputMap::default()
+          .insert(VirtualDPad::wasd(), Action::Move)
+          .insert(KeyCode::Q, Action::Attack)
+          .build(),
+      },
+      &asset_server,
+      &state,
+    );
+  }
+}
+
+fn endless_wave(wave: usize, rng: &mut impl Rng) -> Wave {
+  let mut spawn_points = SPAWN_POINTS.to_vec();
+  spawn_points.shuffle(rng);
+
+  let scaling = EnemyScaling {
+    health: 1.0 + 0.15 * (wave - 1) as f32,
+    speed: (1.0 + 0.04 * (wave - 1) as f32).min(2.0),
+  };
+
+  let mut groups = vec![(EnemySpawnerType::Normal, 15 + 5 * wave, 0.3, 0.0)];
+  if wave >= 2 {
+    groups.push((EnemySpawnerType::Elite, wave, 0.5, 5.0));
+  }
+  if wave >= 3 {
+    groups.push((EnemySpawnerType::Ranged, wave / 2, 1.0, 2.0));
+  }
+  if wave >= 4 {
+    groups.push((EnemySpawnerType::Splitter, wave / 3, 2.0, 4.0));
+  }
+  if wave >= 5 {
+    let support = if wave % 2 == 0 {
+      EnemySpawnerType::ShieldBearer
+    } else {
+      EnemySpawnerType::Healer
+    };
+    groups.push((support, 1 + wave / 6, 3.0, 6.0));
+  }
+  if wave % 5 == 0 {
+    groups.push((EnemySpawnerType::Boss, 1, 1.0, 5.0));
+  }
+
+  let mut groups: Vec<SpawnerGroup> = groups
+    .into_iter()
+    .zip(spawn_points.into_iter().cycle())
+    .map(|((enemy_type, count, interval, delay), position)| {
+      SpawnerGroup::new(position, enemy_type, count, interval)
+        .with_delay(delay)
+        .with_scaling(scaling)
+    })
+    .collect();
+
+  // later waves also come from off screen and close in around the players
+  if wave >= 3 {
+    groups.push(
+      SpawnerGroup::new(Vec3::ZERO, EnemySpawnerType::Normal, 4 * wave, 2.0)
+        .with_delay(3.0)
+        .with_scaling(scaling)
+        .with_pattern(SpawnPattern::Edge { count: 4 }),
+    );
+  }
+  if wave % 3 == 0 {
+    groups.push(
+      SpawnerGroup::new(
+        SPAWN_POINTS[wave % SPAWN_POINTS.len()],
+        EnemySpawnerType::Normal,
+        3 * wave,
+        2.5,
+      )
+      .with_delay(6.0)
+      .with_scaling(scaling)
+      .with_pattern(SpawnPattern::Burst {
+        count: 4,
+        spread: 60.0,
+      }),
+    );
+  }
+  if wave % 4 == 0 {
+    groups.push(
+      SpawnerGroup::new(Vec3::ZERO, EnemySpawnerType::Normal, 12, 1.0)
+        .with_delay(8.0)
+        .with_scaling(scaling)
+        .with_pattern(SpawnPattern::Ring {
+          count: 12,
+          radius: 220.0,
+        }),
+    );
+  }
+
+  Wave {
+    name: format!("Wave {}", wave),
+    groups,
+    trigger: WaveTrigger::Cleared,
+    intermission: Some(3.0),
+  }
+}
+
+// waves keep getting queued with increasing counts, health and speed
+fn queue_waves(mut waves: ResMut<LevelWaves>, mut rng: ResMut<GameRng>) {
+  if waves.started >= waves.waves.len() {
+    let wave = waves.waves.len() + 1;
+    waves.waves.push(endless_wave(wave, &mut *rng));
+  }
+}
+
+fn end_condition(
+  players: Query<&Player>,
+  mut game_state: ResMut<State<GameState>>,
+  mut data: ResMut<GameData>,
+  mut stats: ResMut<RunStats>,
+  run: Res<EndlessRun>,
+  waves: Res<LevelWaves>,
+) {
+  // game over, the score is the amount of fully cleared waves
+  if players.is_empty() {
+    let best = &mut data.endless_best[run.players.clamp(1, 2) - 1];
+    *best = (*best).max(waves.completed);
+
+    stats.waves_survived = Some(waves.completed);
+    stats.best_waves = *best;
+    stats.finish(false, &data);
+    game_state.set(GameState::Results).unwrap()
+  }
+}
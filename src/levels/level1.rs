@@ -1,14 +1,15 @@
-use crate::camera;
 use crate::components::*;
 use crate::enemy::*;
+use crate::fixed;
+use crate::hud;
+use crate::levels;
 use crate::map;
 use crate::player::*;
+use crate::props::{self, Loot, PowerUpKind, PropKind, PropPlacement};
+use crate::stats::{self, RunStats};
 use crate::systems;
-use crate::weapons;
-use crate::{
-  despawn_screen, Action, DamageEvent, DespawnEvent, GameData, GameState, LevelEndTimer,
-  TextureAtlasHandles,
-};
+use crate::waves::*;
+use crate::{despawn_screen, Action, GameData, GameState, LevelEndTimer, TextureAtlasHandles};
 
 use bevy::prelude::*;
 use leafwing_input_manager::prelude::*;
@@ -18,35 +19,21 @@ pub struct Level1Plugin;
 impl Plugin for Level1Plugin {
   fn build(&self, app: &mut App) {
     app
-      .add_system_set(SystemSet::on_enter(GameState::Level1).with_system(init))
-      .add_event::<DamageEvent>()
-      .add_event::<DespawnEvent>()
       .add_system_set(
-        SystemSet::on_update(GameState::Level1)
-          .with_system(systems::clean_up_expired)
-          .with_system(systems::animate_sprite)
-          .with_system(player_movement)
-          .with_system(camera::follow_camera.after(player_movement))
-          .with_system(weapons::gun::spawn_projectiles)
-          .with_system(weapons::lightning::spawn_lightning)
-          .with_system(weapons::laser::spawn_laser)
-          .with_system(weapons::laser::update_laser.after(weapons::laser::spawn_laser))
-          .with_system(weapons::laser::handle_laser_collision)
-          .with_system(systems::handle_damage_event)
-          .with_system(enemy_movement)
-          .with_system(generic_spawner)
-          .with_system(handle_explosion)
-          .with_system(systems::handle_collision)
-          .with_system(systems::deal_red_zone_dmg)
-          .with_system(systems::handle_despawn_entity)
-          .with_system(end_condition),
+        SystemSet::on_enter(GameState::Level1)
+          .with_system(init)
+          .with_system(hud::setup_hud)
+          .with_system(stats::reset_run_stats),
       )
+      .add_system_set(levels::frame_systems(GameState::Level1).with_system(end_condition.at_end()))
       // When exiting the state, despawn everything that was spawned for this screen
       .add_system_set(
         SystemSet::on_exit(GameState::Level1)
           .with_system(despawn_screen::<OnGameScreen>)
           .with_system(systems::save_game),
       );
+
+    fixed::add_gameplay_systems(app, levels::gameplay_systems(GameState::Level1));
   }
 }
 
@@ -59,45 +46,59 @@ fn init(
 ) {
   level_end_timer.timer.reset();
 
-  map::create_map_boundary(&mut commands);
-
-  commands.spawn((
-    OnGameScreen,
-    SpriteBundle {
-      texture: asset_server.load("cave.png"),
-      transform: Transform {
-        translation: Vec3::new(-150., -60., 0.0),
-        ..default()
-      },
-      ..default()
-    },
-    EnemySpawner {
-      timer: Timer::from_seconds(0.7, TimerMode::Repeating),
-      initial_delay: Timer::from_seconds(2.0, TimerMode::Once),
-      spawn_count: 0,
-      spawn_limit: 30,
-      enemy_type: EnemySpawnerType::Normal,
+  map::create_map_boundary(
+    &mut commands,
+    map::ArenaShape::Rectangle {
+      half_size: Vec2::splat(600.0),
     },
-  ));
-
-  commands.spawn((
-    OnGameScreen,
-    SpriteBundle {
-      texture: asset_server.load("cave.png"),
-      transform: Transform {
-        translation: Vec3::new(400., 400., 0.0),
-        ..default()
+  );
+  map::create_scenery(
+    &mut commands,
+    &[
+      map::Scenery::Rock {
+        position: Vec2::new(250.0, -250.0),
+        radius: 30.0,
+      },
+      map::Scenery::Rock {
+        position: Vec2::new(-300.0, 250.0),
+        radius: 40.0,
       },
-      ..default()
+    ],
+  );
+  props::create_props(
+    &mut commands,
+    &textures,
+    &[
+      PropPlacement::new(PropKind::Crate, Vec2::new(-120.0, 150.0)).with_loot(Loot::Money(15)),
+      PropPlacement::new(PropKind::Barrel, Vec2::new(150.0, 120.0))
+        .with_loot(Loot::PowerUp(PowerUpKind::RapidFire)),
+    ],
+  );
+
+  commands.insert_resource(LevelWaves::new(vec![
+    Wave {
+      name: "Wave 1".into(),
+      groups: vec![SpawnerGroup::new(
+        Vec3::new(-150., -60., 0.0),
+        EnemySpawnerType::Normal,
+        30,
+        0.7,
+      )],
+      trigger: WaveTrigger::Timed(23.0),
+      intermission: None,
     },
-    EnemySpawner {
-      timer: Timer::from_seconds(0.01, TimerMode::Repeating),
-      initial_delay: Timer::from_seconds(25.0, TimerMode::Once),
-      spawn_count: 0,
-      spawn_limit: 16,
-      enemy_type: EnemySpawnerType::Elite,
+    Wave {
+      name: "Elites incoming".into(),
+      groups: vec![SpawnerGroup::new(
+        Vec3::new(400., 400., 0.0),
+        EnemySpawnerType::Elite,
+        16,
+        0.01,
+      )],
+      trigger: WaveTrigger::Cleared,
+      intermission: None,
     },
-  ));
+  ]));
 
   create_player(
     &mut commands,
@@ -130,6 +131,7 @@ fn init(
   );
 }
 
+#[allow(clippy::too_many_arguments)]
 fn end_condition(
   players: Query<&Player>,
   enemies: Query<&Enemy>,
@@ -138,9 +140,12 @@ fn end_condition(
   mut data: ResMut<GameData>,
   mut level_end_timer: ResMut<LevelEndTimer>,
   time: Res<Time>,
+  mut stats: ResMut<RunStats>,
+  waves: Res<LevelWaves>,
 ) {
   // game won
-  if enemies.is_empty()
+  if waves.is_finished()
+    && enemies.is_empty()
     && spawners
       .iter()
       .all(|spawner| spawner.spawn_count >= spawner.spawn_limit)
@@ -148,14 +153,18 @@ fn end_condition(
     if level_end_timer.timer.tick(time.delta()).finished() {
       if data.level < 2 {
         data.level = 2;
+        stats.unlock = Some(2);
       }
       data.money += 50;
-      game_state.set(GameState::LevelSelect).unwrap()
+      stats.finish(true, &data);
+      game_state.set(GameState::Results).unwrap();
+      return;
     }
   }
 
   // game lost
   if players.is_empty() {
-    game_state.set(GameState::LevelSelect).unwrap()
+    stats.finish(false, &data);
+    game_state.set(GameState::Results).unwrap()
   }
 }
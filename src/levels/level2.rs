@@ -1,14 +1,16 @@
-use crate::camera;
 use crate::components::*;
 use crate::enemy::*;
+use crate::fixed;
+use crate::hazard::{self, HazardEffect, HazardTarget, HazardZone};
+use crate::hud;
+use crate::levels;
 use crate::map;
 use crate::player::*;
+use crate::props::{self, Loot, PowerUpKind, PropKind, PropPlacement};
+use crate::stats::{self, RunStats};
 use crate::systems;
-use crate::weapons;
-use crate::{
-  despawn_screen, Action, DamageEvent, DespawnEvent, GameData, GameState, LevelEndTimer,
-  TextureAtlasHandles,
-};
+use crate::waves::*;
+use crate::{despawn_screen, Action, GameData, GameState, LevelEndTimer, TextureAtlasHandles};
 
 use bevy::prelude::*;
 use leafwing_input_manager::prelude::*;
@@ -18,35 +20,21 @@ pub struct Level2Plugin;
 impl Plugin for Level2Plugin {
   fn build(&self, app: &mut App) {
     app
-      .add_system_set(SystemSet::on_enter(GameState::Level2).with_system(init))
-      .add_event::<DamageEvent>()
-      .add_event::<DespawnEvent>()
       .add_system_set(
-        SystemSet::on_update(GameState::Level2)
-          .with_system(systems::clean_up_expired)
-          .with_system(systems::animate_sprite)
-          .with_system(player_movement)
-          .with_system(camera::follow_camera.after(player_movement))
-          .with_system(weapons::gun::spawn_projectiles)
-          .with_system(weapons::lightning::spawn_lightning)
-          .with_system(weapons::laser::spawn_laser)
-          .with_system(weapons::laser::update_laser.after(weapons::laser::spawn_laser))
-          .with_system(weapons::laser::handle_laser_collision)
-          .with_system(systems::handle_damage_event)
-          .with_system(enemy_movement)
-          .with_system(generic_spawner)
-          .with_system(handle_explosion)
-          .with_system(systems::handle_collision)
-          .with_system(systems::deal_red_zone_dmg)
-          .with_system(systems::handle_despawn_entity)
-          .with_system(end_condition),
+        SystemSet::on_enter(GameState::Level2)
+          .with_system(init)
+          .with_system(hud::setup_hud)
+          .with_system(stats::reset_run_stats),
       )
+      .add_system_set(levels::frame_systems(GameState::Level2).with_system(end_condition.at_end()))
       // When exiting the state, despawn everything that was spawned for this screen
       .add_system_set(
         SystemSet::on_exit(GameState::Level2)
           .with_system(despawn_screen::<OnGameScreen>)
           .with_system(systems::save_game),
       );
+
+    fixed::add_gameplay_systems(app, levels::gameplay_systems(GameState::Level2));
   }
 }
 
@@ -59,102 +47,92 @@ pub fn init(
 ) {
   level_end_timer.timer.reset();
 
-  map::create_map_boundary(&mut commands);
-
-  commands.spawn((
-    OnGameScreen,
-    SpriteBundle {
-      texture: asset_server.load("cave.png"),
-      transform: Transform {
-        translation: Vec3::new(0., 0., 0.0),
-        ..default()
+  map::create_map_boundary(&mut commands, map::ArenaShape::Circle { radius: 600.0 });
+  hazard::create_hazards(
+    &mut commands,
+    &[
+      HazardZone::new(
+        Vec2::new(-300.0, 0.0),
+        80.0,
+        HazardEffect::Speed(0.5),
+        HazardTarget::Both,
+      ),
+      HazardZone::new(
+        Vec2::new(300.0, 0.0),
+        50.0,
+        HazardEffect::Heal(5.0),
+        HazardTarget::Players,
+      )
+      .with_pulse(4.0, 6.0),
+    ],
+  );
+  map::create_scenery(
+    &mut commands,
+    &[
+      map::Scenery::Pillar {
+        position: Vec2::new(200.0, 200.0),
+        radius: 20.0,
       },
-      ..default()
-    },
-    EnemySpawner {
-      timer: Timer::from_seconds(0.3, TimerMode::Repeating),
-      initial_delay: Timer::from_seconds(2.0, TimerMode::Once),
-      spawn_count: 0,
-      spawn_limit: 60,
-      enemy_type: EnemySpawnerType::Normal,
-    },
-  ));
-
-  commands.spawn((
-    OnGameScreen,
-    SpriteBundle {
-      texture: asset_server.load("cave.png"),
-      transform: Transform {
-        translation: Vec3::new(400., -400., 0.0),
-        ..default()
+      map::Scenery::Pillar {
+        position: Vec2::new(-200.0, 200.0),
+        radius: 20.0,
       },
-      ..default()
-    },
-    EnemySpawner {
-      timer: Timer::from_seconds(0.01, TimerMode::Repeating),
-      initial_delay: Timer::from_seconds(25.0, TimerMode::Once),
-      spawn_count: 0,
-      spawn_limit: 12,
-      enemy_type: EnemySpawnerType::Elite,
-    },
-  ));
-
-  commands.spawn((
-    OnGameScreen,
-    SpriteBundle {
-      texture: asset_server.load("cave.png"),
-      transform: Transform {
-        translation: Vec3::new(-400., -400., 0.0),
-        ..default()
+      map::Scenery::Pillar {
+        position: Vec2::new(200.0, -200.0),
+        radius: 20.0,
       },
-      ..default()
-    },
-    EnemySpawner {
-      timer: Timer::from_seconds(0.01, TimerMode::Repeating),
-      initial_delay: Timer::from_seconds(25.0, TimerMode::Once),
-      spawn_count: 0,
-      spawn_limit: 12,
-      enemy_type: EnemySpawnerType::Elite,
-    },
-  ));
-
-  commands.spawn((
-    OnGameScreen,
-    SpriteBundle {
-      texture: asset_server.load("cave.png"),
-      transform: Transform {
-        translation: Vec3::new(-400., 400., 0.0),
-        ..default()
+      map::Scenery::Pillar {
+        position: Vec2::new(-200.0, -200.0),
+        radius: 20.0,
       },
-      ..default()
-    },
-    EnemySpawner {
-      timer: Timer::from_seconds(0.01, TimerMode::Repeating),
-      initial_delay: Timer::from_seconds(25.0, TimerMode::Once),
-      spawn_count: 0,
-      spawn_limit: 12,
-      enemy_type: EnemySpawnerType::Elite,
-    },
-  ));
+    ],
+  );
+  props::create_props(
+    &mut commands,
+    &textures,
+    &[
+      PropPlacement::new(PropKind::Crate, Vec2::new(0.0, 250.0)).with_loot(Loot::Money(25)),
+      PropPlacement::new(PropKind::Crate, Vec2::new(0.0, -250.0)).with_loot(Loot::Money(25)),
+      PropPlacement::new(PropKind::Barrel, Vec2::new(-120.0, 0.0))
+        .with_loot(Loot::PowerUp(PowerUpKind::Haste)),
+    ],
+  );
 
-  commands.spawn((
-    OnGameScreen,
-    SpriteBundle {
-      texture: asset_server.load("cave.png"),
-      transform: Transform {
-        translation: Vec3::new(400., 400., 0.0),
-        ..default()
-      },
-      ..default()
+  commands.insert_resource(LevelWaves::new(vec![
+    Wave {
+      name: "Wave 1".into(),
+      groups: vec![
+        SpawnerGroup::new(Vec3::new(0., 0., 0.0), EnemySpawnerType::Normal, 60, 0.3),
+        // closes in around the players every few seconds
+        SpawnerGroup::new(Vec3::new(0., 0., 0.0), EnemySpawnerType::Normal, 24, 6.0)
+          .with_delay(5.0)
+          .with_pattern(SpawnPattern::Ring {
+            count: 8,
+            radius: 250.0,
+          }),
+      ],
+      trigger: WaveTrigger::Timed(23.0),
+      intermission: None,
     },
-    EnemySpawner {
-      timer: Timer::from_seconds(0.01, TimerMode::Repeating),
-      initial_delay: Timer::from_seconds(25.0, TimerMode::Once),
-      spawn_count: 0,
-      spawn_limit: 12,
-      enemy_type: EnemySpawnerType::Elite,
+    Wave {
+      name: "Elites from every corner".into(),
+      groups: [
+        Vec3::new(400., -400., 0.0),
+        Vec3::new(-400., -400., 0.0),
+        Vec3::new(-400., 400., 0.0),
+        Vec3::new(400., 400., 0.0),
+      ]
+      .into_iter()
+      .map(|position| SpawnerGroup::new(position, EnemySpawnerType::Elite, 12, 0.01))
+      .chain([
+        SpawnerGroup::new(Vec3::new(0., -400., 0.0), EnemySpawnerType::Ranged, 6, 1.0),
+        SpawnerGroup::new(Vec3::new(0., 400., 0.0), EnemySpawnerType::Healer, 2, 1.0),
+      ])
+      .collect(),
+      trigger: WaveTrigger::Cleared,
+      intermission: None,
     },
-  ));
+  ]));
 
   create_player(
     &mut commands,
@@ -187,6 +165,7 @@ pub fn init(
   );
 }
 
+#[allow(clippy::too_many_arguments)]
 fn end_condition(
   players: Query<&Player>,
   enemies: Query<&Enemy>,
@@ -195,9 +174,12 @@ fn end_condition(
   mut level_end_timer: ResMut<LevelEndTimer>,
   time: Res<Time>,
   mut data: ResMut<GameData>,
+  mut stats: ResMut<RunStats>,
+  waves: Res<LevelWaves>,
 ) {
   // game won
-  if enemies.is_empty()
+  if waves.is_finished()
+    && enemies.is_empty()
     && spawners
       .iter()
       .all(|spawner| spawner.spawn_count >= spawner.spawn_limit)
@@ -205,14 +187,18 @@ fn end_condition(
     if level_end_timer.timer.tick(time.delta()).finished() {
       if data.level < 3 {
         data.level = 3;
+        stats.unlock = Some(3);
       }
       data.money += 100;
-      game_state.set(GameState::LevelSelect).unwrap()
+      stats.finish(true, &data);
+      game_state.set(GameState::Results).unwrap();
+      return;
     }
   }
 
   // game lost
   if players.is_empty() {
-    game_state.set(GameState::LevelSelect).unwrap()
+    stats.finish(false, &data);
+    game_state.set(GameState::Results).unwrap()
   }
 }
@@ -1,53 +1,51 @@
-use crate::camera;
 use crate::components::*;
 use crate::enemy::*;
+use crate::fixed;
+use crate::hazard::{self, HazardEffect, HazardMotion, HazardTarget, HazardZone};
+use crate::hud;
+use crate::levels;
 use crate::map;
 use crate::player::*;
+use crate::props::{self, Loot, PowerUpKind, PropKind, PropPlacement};
+use crate::stats::{self, RunStats};
 use crate::systems;
-use crate::weapons;
-use crate::{
-  despawn_screen, Action, DamageEvent, DespawnEvent, GameData, GameState, LevelEndTimer,
-  TextureAtlasHandles,
-};
+use crate::waves::*;
+use crate::{despawn_screen, Action, GameData, GameState, LevelEndTimer, TextureAtlasHandles};
 
 use bevy::prelude::*;
 use leafwing_input_manager::prelude::*;
 
 pub struct Level3Plugin;
 
+pub const ARENA_CORNERS: [Vec2; 8] = [
+  Vec2::new(-600.0, -350.0),
+  Vec2::new(-250.0, -600.0),
+  Vec2::new(300.0, -550.0),
+  Vec2::new(600.0, -200.0),
+  Vec2::new(550.0, 300.0),
+  Vec2::new(200.0, 600.0),
+  Vec2::new(-350.0, 550.0),
+  Vec2::new(-600.0, 200.0),
+];
+
 impl Plugin for Level3Plugin {
   fn build(&self, app: &mut App) {
     app
-      .add_system_set(SystemSet::on_enter(GameState::Level3).with_system(init))
-      .add_event::<DamageEvent>()
-      .add_event::<DespawnEvent>()
       .add_system_set(
-        SystemSet::on_update(GameState::Level3)
-          .with_system(systems::clean_up_expired)
-          .with_system(systems::animate_sprite)
-          .with_system(player_movement)
-          .with_system(camera::follow_camera.after(player_movement))
-          .with_system(weapons::gun::spawn_projectiles)
-          .with_system(weapons::lightning::spawn_lightning)
-          .with_system(weapons::laser::spawn_laser)
-          .with_system(weapons::laser::update_laser.after(weapons::laser::spawn_laser))
-          .with_system(weapons::laser::handle_laser_collision)
-          .with_system(systems::handle_damage_event)
-          .with_system(enemy_movement)
-          .with_system(handle_charge.after(enemy_movement))
-          .with_system(generic_spawner)
-          .with_system(handle_explosion)
-          .with_system(systems::handle_collision)
-          .with_system(systems::deal_red_zone_dmg)
-          .with_system(systems::handle_despawn_entity.at_end())
-          .with_system(end_condition.at_end()),
+        SystemSet::on_enter(GameState::Level3)
+          .with_system(init)
+          .with_system(hud::setup_hud)
+          .with_system(stats::reset_run_stats),
       )
+      .add_system_set(levels::frame_systems(GameState::Level3).with_system(end_condition.at_end()))
       // When exiting the state, despawn everything that was spawned for this screen
       .add_system_set(
         SystemSet::on_exit(GameState::Level3)
           .with_system(despawn_screen::<OnGameScreen>)
           .with_system(systems::save_game),
       );
+
+    fixed::add_gameplay_systems(app, levels::gameplay_systems(GameState::Level3));
   }
 }
 
@@ -60,26 +58,95 @@ pub fn init(
 ) {
   level_end_timer.timer.reset();
 
-  map::create_map_boundary(&mut commands);
-
-  commands.spawn((
-    OnGameScreen,
-    SpriteBundle {
-      texture: asset_server.load("cave.png"),
-      transform: Transform {
-        translation: Vec3::new(0., 0., 0.0),
-        ..default()
+  map::create_map_boundary(
+    &mut commands,
+    map::ArenaShape::Polygon {
+      points: ARENA_CORNERS.to_vec(),
+    },
+  );
+  hazard::create_hazards(
+    &mut commands,
+    &[
+      HazardZone::new(
+        Vec2::ZERO,
+        60.0,
+        HazardEffect::Damage(15.0),
+        HazardTarget::Both,
+      )
+      .with_motion(HazardMotion::Orbit {
+        radius: 380.0,
+        period: 20.0,
+      }),
+      HazardZone::new(
+        Vec2::new(-350.0, -300.0),
+        60.0,
+        HazardEffect::Speed(1.5),
+        HazardTarget::Enemies,
+      ),
+    ],
+  );
+  map::create_scenery(
+    &mut commands,
+    &[
+      map::Scenery::Wall {
+        from: Vec2::new(-150.0, 250.0),
+        to: Vec2::new(150.0, 250.0),
+        thickness: 20.0,
+      },
+      map::Scenery::Wall {
+        from: Vec2::new(-150.0, -250.0),
+        to: Vec2::new(150.0, -250.0),
+        thickness: 20.0,
+      },
+      map::Scenery::Rock {
+        position: Vec2::new(350.0, 0.0),
+        radius: 35.0,
       },
-      ..default()
+    ],
+  );
+  props::create_props(
+    &mut commands,
+    &textures,
+    &[
+      PropPlacement::new(PropKind::Crate, Vec2::new(0.0, 220.0)).with_loot(Loot::Money(40)),
+      PropPlacement::new(PropKind::Crate, Vec2::new(0.0, -220.0)).with_loot(Loot::Money(40)),
+      PropPlacement::new(PropKind::Barrel, Vec2::new(200.0, 100.0))
+        .with_loot(Loot::PowerUp(PowerUpKind::RapidFire)),
+      PropPlacement::new(PropKind::Barrel, Vec2::new(200.0, -100.0))
+        .with_loot(Loot::PowerUp(PowerUpKind::Haste)),
+    ],
+  );
+
+  commands.insert_resource(LevelWaves::new(vec![
+    Wave {
+      name: "Slimes that split".into(),
+      groups: vec![
+        SpawnerGroup::new(
+          Vec3::new(-400., 0., 0.0),
+          EnemySpawnerType::Splitter,
+          6,
+          2.0,
+        ),
+        SpawnerGroup::new(
+          Vec3::new(-400., 0., 0.0),
+          EnemySpawnerType::ShieldBearer,
+          2,
+          5.0,
+        )
+        .with_delay(1.0),
+      ],
+      trigger: WaveTrigger::Cleared,
+      intermission: Some(3.0),
     },
-    EnemySpawner {
-      timer: Timer::from_seconds(1.0, TimerMode::Repeating),
-      initial_delay: Timer::from_seconds(5.0, TimerMode::Once),
-      spawn_count: 0,
-      spawn_limit: 1,
-      enemy_type: EnemySpawnerType::Boss,
+    Wave {
+      name: "The final battle".into(),
+      groups: vec![
+        SpawnerGroup::new(Vec3::new(0., 0., 0.0), EnemySpawnerType::Boss, 1, 1.0).with_delay(3.0),
+      ],
+      trigger: WaveTrigger::Cleared,
+      intermission: None,
     },
-  ));
+  ]));
 
   create_player(
     &mut commands,
@@ -112,6 +179,7 @@ pub fn init(
   );
 }
 
+#[allow(clippy::too_many_arguments)]
 fn end_condition(
   players: Query<&Player>,
   enemies: Query<&Enemy>,
@@ -119,20 +187,27 @@ fn end_condition(
   mut game_state: ResMut<State<GameState>>,
   time: Res<Time>,
   mut level_end_timer: ResMut<LevelEndTimer>,
+  data: Res<GameData>,
+  mut stats: ResMut<RunStats>,
+  waves: Res<LevelWaves>,
 ) {
   // game won
-  if enemies.is_empty()
+  if waves.is_finished()
+    && enemies.is_empty()
     && spawners
       .iter()
       .all(|spawner| spawner.spawn_count >= spawner.spawn_limit)
   {
     if level_end_timer.timer.tick(time.delta()).finished() {
-      game_state.set(GameState::LevelSelect).unwrap()
+      stats.finish(true, &data);
+      game_state.set(GameState::Results).unwrap();
+      return;
     }
   }
 
   // game lost
   if players.is_empty() {
-    game_state.set(GameState::LevelSelect).unwrap()
+    stats.finish(false, &data);
+    game_state.set(GameState::Results).unwrap()
   }
 }
@@ -1,3 +1,105 @@
+use crate::ai;
+use crate::camera;
+use crate::coins;
+use crate::damage_text;
+use crate::enemy::*;
+use crate::fixed;
+use crate::hazard;
+use crate::hud;
+use crate::navigation;
+use crate::player::*;
+use crate::props;
+use crate::stats;
+use crate::systems;
+use crate::waves;
+use crate::weapons;
+use crate::GameState;
+
+use bevy::prelude::*;
+
+pub mod endless;
 pub mod level1;
 pub mod level2;
 pub mod level3;
+
+// a step runs through these in order, so whatever is hit in a step takes damage, dies and is
+// removed in that same step
+#[derive(SystemLabel, Clone, Copy)]
+pub enum GameplaySystem {
+  Input,
+  Movement,
+  Weapons,
+  Collision,
+  Damage,
+  Death,
+  Cleanup,
+}
+
+// systems shared by every level that advance the simulation, one set per stage of a step. the
+// systems of a stage are chained as bevy picks a different order for unordered systems in every
+// app, and the order decides how the rng is drawn from and in which order entities are spawned
+pub fn gameplay_systems(state: GameState) -> Vec<SystemSet> {
+  let stage = |label: GameplaySystem| fixed::on_step(state.clone()).label(label);
+
+  vec![
+    stage(GameplaySystem::Input)
+      .with_system(player_movement)
+      .with_system(props::update_power_ups.after(player_movement))
+      .with_system(navigation::update_nav_grid.after(props::update_power_ups))
+      .with_system(navigation::update_flow_fields.after(navigation::update_nav_grid)),
+    stage(GameplaySystem::Movement)
+      .after(GameplaySystem::Input)
+      .with_system(generic_spawner)
+      .with_system(waves::run_waves.after(generic_spawner))
+      .with_system(hazard::update_hazards.after(waves::run_waves))
+      .with_system(ai::update_enemy_ai.after(hazard::update_hazards)),
+    stage(GameplaySystem::Weapons)
+      .after(GameplaySystem::Movement)
+      .with_system(weapons::gun::spawn_projectiles)
+      .with_system(weapons::lightning::spawn_lightning.after(weapons::gun::spawn_projectiles))
+      .with_system(weapons::laser::spawn_laser.after(weapons::lightning::spawn_lightning))
+      .with_system(weapons::laser::update_laser.after(weapons::laser::spawn_laser))
+      .with_system(enemy_shooting.after(weapons::laser::update_laser))
+      .with_system(heal_allies.after(enemy_shooting))
+      .with_system(update_shields.after(heal_allies)),
+    stage(GameplaySystem::Collision)
+      .after(GameplaySystem::Weapons)
+      .with_system(systems::handle_collision)
+      .with_system(weapons::laser::handle_laser_collision.after(systems::handle_collision))
+      .with_system(hazard::apply_hazards.after(weapons::laser::handle_laser_collision))
+      .with_system(props::collect_power_ups.after(hazard::apply_hazards))
+      .with_system(coins::collect_coins.after(props::collect_power_ups))
+      .with_system(systems::clean_up_expired.after(coins::collect_coins)),
+    stage(GameplaySystem::Damage)
+      .after(GameplaySystem::Collision)
+      .with_system(systems::handle_damage_event)
+      .with_system(systems::handle_knockback.after(systems::handle_damage_event))
+      .with_system(damage_text::spawn_damage_text.after(systems::handle_knockback))
+      .with_system(stats::track_damage.after(damage_text::spawn_damage_text))
+      .with_system(stats::track_run_time.after(stats::track_damage)),
+    stage(GameplaySystem::Death)
+      .after(GameplaySystem::Damage)
+      .with_system(handle_enemy_death)
+      .with_system(props::handle_prop_destroyed.after(handle_enemy_death)),
+    stage(GameplaySystem::Cleanup)
+      .after(GameplaySystem::Death)
+      .with_system(systems::handle_despawn_entity),
+  ]
+}
+
+// systems shared by every level that only affect what is shown, run every frame
+pub fn frame_systems(state: GameState) -> SystemSet {
+  SystemSet::on_update(state)
+    .with_system(buffer_attack_input)
+    .with_system(systems::animate_sprite)
+    .with_system(camera::follow_camera)
+    .with_system(damage_text::animate_floating_text)
+    .with_system(hud::spawn_player_panels)
+    .with_system(hud::update_health_bars)
+    .with_system(hud::update_cooldown_bars)
+    .with_system(hud::update_money_text)
+    .with_system(hud::update_progress_text)
+    .with_system(hud::spawn_boss_bar)
+    .with_system(hud::update_boss_bar)
+    .with_system(hud::fade_banners)
+}
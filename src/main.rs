@@ -7,14 +7,28 @@ use serde::{Deserialize, Serialize};
 
 use bevy_inspector_egui::quick::WorldInspectorPlugin;
 
+mod ai;
 mod camera;
+mod coins;
 mod components;
+mod damage_text;
 mod enemy;
+mod fixed;
+mod hazard;
+mod hud;
 mod levels;
 mod map;
 mod menu;
+mod navigation;
 mod player;
+mod pool;
+mod props;
+mod spatial;
+mod stats;
 mod systems;
+#[cfg(test)]
+mod testing;
+mod waves;
 mod weapons;
 
 #[derive(Clone, Eq, PartialEq, Debug, Hash)]
@@ -22,9 +36,22 @@ pub enum GameState {
   MainMenu,
   LevelSelect,
   Upgrades,
+  Settings,
   Level1,
   Level2,
   Level3,
+  Endless,
+  Paused,
+  Results,
+}
+
+impl GameState {
+  pub fn is_level(&self) -> bool {
+    matches!(
+      self,
+      GameState::Level1 | GameState::Level2 | GameState::Level3 | GameState::Endless
+    )
+  }
 }
 
 #[derive(Resource, Default)]
@@ -33,6 +60,7 @@ pub struct TextureAtlasHandles {
   pub elite_atlas_handle: Handle<TextureAtlas>,
   pub boss_atlas_handle: Handle<TextureAtlas>,
   pub player_atlas_handle: Handle<TextureAtlas>,
+  pub props_atlas_handle: Handle<TextureAtlas>,
 }
 
 pub fn despawn_screen<T: Component>(to_despawn: Query<Entity, With<T>>, mut commands: Commands) {
@@ -42,16 +70,24 @@ pub fn despawn_screen<T: Component>(to_despawn: Query<Entity, With<T>>, mut comm
 }
 
 #[derive(Resource, Serialize, Deserialize, Debug)]
+#[serde(default)]
 pub struct GameData {
   new_game: bool,
   level: usize,
   money: i32,
   player_ms: f32,
+  // how close coins have to be before they fly to the player
+  magnet_radius: f32,
   camera_pos: Vec2,
   gun_cooldown: f32,
   gun_damage: f32,
+  gun_knockback: f32,
+  crit_chance: f32,
+  crit_multiplier: f32,
   lightning_gun: weapons::lightning::LightningGunConfig,
   laser_gun: weapons::laser::LaserGunConfig,
+  // most waves survived in endless mode, indexed by player count - 1
+  endless_best: [usize; 2],
 }
 
 impl Default for GameData {
@@ -61,18 +97,38 @@ impl Default for GameData {
       level: 1,
       money: 200,
       player_ms: 100.0,
+      magnet_radius: 40.0,
       camera_pos: Vec2::default(),
       gun_cooldown: 1.5,
       gun_damage: 20.0,
+      gun_knockback: 150.0,
+      crit_chance: 0.05,
+      crit_multiplier: 1.5,
       lightning_gun: weapons::lightning::LightningGunConfig {
         cooldown: 10.0,
         damage: 100.0,
         size: 2.5,
+        knockback: 300.0,
       },
       laser_gun: weapons::laser::LaserGunConfig {
         cooldown: 10.0,
         damage: 500.0,
       },
+      endless_best: [0, 0],
+    }
+  }
+}
+
+#[derive(Resource, Serialize, Deserialize, Debug)]
+#[serde(default)]
+pub struct Settings {
+  damage_numbers: bool,
+}
+
+impl Default for Settings {
+  fn default() -> Self {
+    Settings {
+      damage_numbers: true,
     }
   }
 }
@@ -83,9 +139,42 @@ pub enum Action {
   Attack,
 }
 
+pub struct Knockback {
+  origin: Vec3,
+  strength: f32,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub enum DamageSource {
+  Gun,
+  Lightning,
+  Laser,
+  Killzone,
+  Hazard,
+  Contact,
+  EnemyProjectile,
+}
+
+impl DamageSource {
+  pub fn name(&self) -> &'static str {
+    match self {
+      DamageSource::Gun => "Gun",
+      DamageSource::Lightning => "Lightning",
+      DamageSource::Laser => "Laser",
+      DamageSource::Killzone => "Red zone",
+      DamageSource::Hazard => "Hazard",
+      DamageSource::Contact => "Contact",
+      DamageSource::EnemyProjectile => "Enemy projectile",
+    }
+  }
+}
+
 pub struct DamageEvent {
   entity: Entity,
   damage: f32,
+  crit: bool,
+  knockback: Option<Knockback>,
+  source: DamageSource,
 }
 
 pub struct DespawnEvent {
@@ -118,29 +207,42 @@ fn main() {
         })
         .set(ImagePlugin::default_nearest()),
     )
-    .add_plugin(RapierPhysicsPlugin::<NoUserData>::pixels_per_meter(100.0))
-    .insert_resource(RapierConfiguration {
-      gravity: Vec2::ZERO,
-      ..default()
-    })
+    // physics is stepped by the fixed timestep plugin together with gameplay
+    .add_plugin(
+      RapierPhysicsPlugin::<NoUserData>::pixels_per_meter(100.0).with_default_system_setup(false),
+    )
+    .add_plugin(fixed::FixedTimestepPlugin)
     .insert_resource(ClearColor(Color::rgb(0.7, 0.6, 0.5)))
     // .add_plugin(WorldInspectorPlugin)
     // .add_plugin(RapierDebugRenderPlugin::default())
     .init_resource::<GameData>()
+    .init_resource::<Settings>()
     .init_resource::<LevelEndTimer>()
+    .init_resource::<stats::RunStats>()
+    .init_resource::<levels::endless::EndlessRun>()
+    .init_resource::<waves::LevelWaves>()
+    .init_resource::<damage_text::DamageTextAccumulator>()
+    .init_resource::<spatial::SpatialIndex>()
+    .init_resource::<pool::EntityPools>()
+    .init_resource::<navigation::Navigation>()
+    .init_resource::<map::Arena>()
     .insert_resource(PkvStore::new("Slime", "Game"))
     .add_startup_system(camera::setup_camera)
     .add_startup_system(systems::initialize_texture_atlas)
     .add_startup_system(systems::load_game)
-    .add_system(bevy::window::close_on_esc)
+    .add_startup_system(systems::load_settings)
     .add_state(GameState::MainMenu)
     .add_plugin(ShapePlugin)
     .add_plugin(InputManagerPlugin::<Action>::default())
     .add_plugin(menu::main_menu::MainMenuPlugin)
     .add_plugin(menu::level_select::LevelSelectPlugin)
     .add_plugin(menu::upgrades::UpgradesPlugin)
+    .add_plugin(menu::settings::SettingsPlugin)
+    .add_plugin(menu::pause::PausePlugin)
+    .add_plugin(menu::results::ResultsPlugin)
     .add_plugin(levels::level1::Level1Plugin)
     .add_plugin(levels::level2::Level2Plugin)
     .add_plugin(levels::level3::Level3Plugin)
+    .add_plugin(levels::endless::EndlessPlugin)
     .run();
 }
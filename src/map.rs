@@ -1,27 +1,166 @@
 use crate::components::*;
+use crate::hazard::{Hazard, HazardEffect, HazardTarget};
 use bevy::prelude::*;
 use bevy_rapier2d::prelude::*;
 
 use bevy_prototype_lyon::prelude::FillMode;
 use bevy_prototype_lyon::prelude::*;
 
-pub fn create_map_boundary(commands: &mut Commands) {
-  for n in -30..=30 {
-    let n = n as f32 * 50.0;
-    let shape1 = shapes::Line {
-      0: Vec2::new(n, 5000.0),
-      1: Vec2::new(n, -5000.0),
-    };
+// the playable area of a level, everything outside of it is a killzone
+#[derive(Clone, Debug)]
+pub enum ArenaShape {
+  Rectangle { half_size: Vec2 },
+  Circle { radius: f32 },
+  // corners of an irregular arena in either winding order, the edges may not cross
+  Polygon { points: Vec<Vec2> },
+}
+
+#[derive(Resource, Clone, Debug)]
+pub struct Arena {
+  shape: ArenaShape,
+  // the shape as a counter clockwise polygon, circles get approximated
+  outline: Vec<Vec2>,
+}
+
+impl Default for Arena {
+  fn default() -> Self {
+    Arena::new(ArenaShape::Rectangle {
+      half_size: Vec2::splat(600.0),
+    })
+  }
+}
 
-    let shape2 = shapes::Line {
-      0: Vec2::new(-5000.0, n),
-      1: Vec2::new(5000.0, n),
+impl Arena {
+  pub fn new(shape: ArenaShape) -> Self {
+    let mut outline = match &shape {
+      ArenaShape::Rectangle { half_size } => vec![
+        Vec2::new(-half_size.x, -half_size.y),
+        Vec2::new(half_size.x, -half_size.y),
+        Vec2::new(half_size.x, half_size.y),
+        Vec2::new(-half_size.x, half_size.y),
+      ],
+      ArenaShape::Circle { radius } => (0..48)
+        .map(|n| {
+          let angle = n as f32 * std::f32::consts::TAU / 48.0;
+          Vec2::new(angle.cos(), angle.sin()) * *radius
+        })
+        .collect(),
+      ArenaShape::Polygon { points } => points.clone(),
     };
 
+    if signed_area(&outline) < 0.0 {
+      outline.reverse();
+    }
+
+    Arena { shape, outline }
+  }
+
+  // smallest and largest corner of the box around the arena
+  pub fn bounds(&self) -> (Vec2, Vec2) {
+    self.outline.iter().fold(
+      (Vec2::splat(f32::MAX), Vec2::splat(f32::MIN)),
+      |(min, max), point| (min.min(*point), max.max(*point)),
+    )
+  }
+
+  pub fn contains(&self, point: Vec2) -> bool {
+    match &self.shape {
+      ArenaShape::Rectangle { half_size } => point.abs().cmple(*half_size).all(),
+      ArenaShape::Circle { radius } => point.length() <= *radius,
+      ArenaShape::Polygon { .. } => {
+        // count the edges a ray to the right crosses
+        let mut inside = false;
+        for (a, b) in self.edges() {
+          if (a.y > point.y) != (b.y > point.y)
+            && point.x < a.x + (point.y - a.y) / (b.y - a.y) * (b.x - a.x)
+          {
+            inside = !inside;
+          }
+        }
+        inside
+      }
+    }
+  }
+
+  // the closest point that is inside the arena and at least `margin` away from its edge
+  pub fn clamp(&self, point: Vec2, margin: f32) -> Vec2 {
+    match &self.shape {
+      ArenaShape::Rectangle { half_size } => {
+        let limit = (*half_size - margin).max(Vec2::ZERO);
+        point.clamp(-limit, limit)
+      }
+      ArenaShape::Circle { radius } => point.clamp_length_max((*radius - margin).max(0.0)),
+      ArenaShape::Polygon { .. } => {
+        let (closest, inward) = self
+          .edges()
+          .map(|(a, b)| {
+            let edge = b - a;
+            let t = ((point - a).dot(edge) / edge.length_squared()).clamp(0.0, 1.0);
+            (a + edge * t, edge.perp().normalize_or_zero())
+          })
+          .min_by(|(a, _), (b, _)| {
+            a.distance_squared(point)
+              .total_cmp(&b.distance_squared(point))
+          })
+          .unwrap_or((point, Vec2::ZERO));
+
+        if self.contains(point) && closest.distance(point) >= margin {
+          point
+        } else {
+          closest + inward * margin
+        }
+      }
+    }
+  }
+
+  fn edges(&self) -> impl Iterator<Item = (Vec2, Vec2)> + '_ {
+    self
+      .outline
+      .iter()
+      .zip(self.outline.iter().cycle().skip(1))
+      .map(|(a, b)| (*a, *b))
+  }
+}
+
+// the killzone reaches this far past the arena
+const KILLZONE_EXTENT: f32 = 5000.0;
+
+// static obstacles placed by the level data
+#[derive(Clone, Copy, Debug)]
+pub enum Scenery {
+  Rock {
+    position: Vec2,
+    radius: f32,
+  },
+  Pillar {
+    position: Vec2,
+    radius: f32,
+  },
+  Wall {
+    from: Vec2,
+    to: Vec2,
+    thickness: f32,
+  },
+}
+
+pub fn create_map_boundary(commands: &mut Commands, shape: ArenaShape) {
+  let arena = Arena::new(shape);
+  let (min, max) = arena.bounds();
+
+  // grid lines every 50 units over the arena and a little past it
+  let grid_min = ((min - 100.0) / 50.0).floor() * 50.0;
+  let grid_max = ((max + 100.0) / 50.0).ceil() * 50.0;
+
+  let mut x = grid_min.x;
+  while x <= grid_max.x {
+    let line = shapes::Line {
+      0: Vec2::new(x, grid_min.y),
+      1: Vec2::new(x, grid_max.y),
+    };
     commands.spawn((
       OnGameScreen,
       GeometryBuilder::build_as(
-        &shape1,
+        &line,
         DrawMode::Outlined {
           fill_mode: FillMode::color(Color::BLACK),
           outline_mode: StrokeMode::new(Color::rgba(0.0, 0.0, 0.0, 0.2), 1.0),
@@ -29,10 +168,19 @@ pub fn create_map_boundary(commands: &mut Commands) {
         Transform::from_translation(Vec3::new(0.0, 0.0, 0.0)),
       ),
     ));
+    x += 50.0;
+  }
+
+  let mut y = grid_min.y;
+  while y <= grid_max.y {
+    let line = shapes::Line {
+      0: Vec2::new(grid_min.x, y),
+      1: Vec2::new(grid_max.x, y),
+    };
     commands.spawn((
       OnGameScreen,
       GeometryBuilder::build_as(
-        &shape2,
+        &line,
         DrawMode::Outlined {
           fill_mode: FillMode::color(Color::BLACK),
           outline_mode: StrokeMode::new(Color::rgba(0.0, 0.0, 0.0, 0.1), 1.0),
@@ -40,55 +188,378 @@ pub fn create_map_boundary(commands: &mut Commands) {
         Transform::from_translation(Vec3::new(0.0, 0.0, 0.0)),
       ),
     ));
+    y += 50.0;
   }
 
-  let map_size = 600.0;
+  // everything between a big box around the arena and the arena itself
+  let outer_min = min - KILLZONE_EXTENT;
+  let outer_max = max + KILLZONE_EXTENT;
 
-  let map_size_50 = map_size * 50.0;
-  let map_size_25 = map_size * 25.0;
+  let mut path = PathBuilder::new();
+  path.move_to(outer_min);
+  path.line_to(Vec2::new(outer_max.x, outer_min.y));
+  path.line_to(outer_max);
+  path.line_to(Vec2::new(outer_min.x, outer_max.y));
+  path.close();
+  path.move_to(arena.outline[0]);
+  for point in &arena.outline[1..] {
+    path.line_to(*point);
+  }
+  path.close();
 
-  for (translate, size, cuboid) in [
-    (
-      (0.0, -map_size - map_size_25, 0.0),
-      (map_size_50, map_size_50),
-      (map_size_25, map_size_25),
-    ),
-    (
-      (0.0, map_size + map_size_25, 0.0),
-      (map_size_50, map_size_50),
-      (map_size_25, map_size_25),
-    ),
-    (
-      (-map_size - map_size_25, 0.0, 0.0),
-      (map_size_50, map_size * 2.0),
-      (map_size_25, map_size_25),
-    ),
-    (
-      (map_size + map_size_25, 0.0, 0.0),
-      (map_size_50, map_size * 2.0),
-      (map_size_25, map_size_25),
+  let triangles = triangulate(&killzone_outline(&arena.outline, outer_min, outer_max));
+  let collider = Collider::compound(
+    triangles
+      .into_iter()
+      .map(|[a, b, c]| (Vec2::ZERO, 0.0, Collider::triangle(a, b, c)))
+      .collect(),
+  );
+
+  commands.spawn((
+    OnGameScreen,
+    Killzone,
+    Hazard::new(HazardEffect::Damage(20.0), HazardTarget::Both),
+    GeometryBuilder::build_as(
+      &path.build(),
+      DrawMode::Outlined {
+        fill_mode: FillMode {
+          options: FillOptions::even_odd(),
+          color: Color::rgba(1.0, 0.0, 0.0, 0.3),
+        },
+        outline_mode: StrokeMode::new(Color::rgba(1.0, 0.0, 0.0, 0.0), 0.0),
+      },
+      Transform::from_translation(Vec3::new(0.0, 0.0, 0.0)),
     ),
-  ] {
-    let square = shapes::Rectangle {
-      extents: Vec2::new(size.0, size.1),
-      origin: RectangleOrigin::Center,
+    collider,
+    Sensor,
+    CollidingEntities::default(),
+    RigidBody::Fixed,
+    CollisionGroups::new(Group::GROUP_6, Group::ALL),
+  ));
+
+  commands.insert_resource(arena);
+}
+
+fn signed_area(points: &[Vec2]) -> f32 {
+  points
+    .iter()
+    .zip(points.iter().cycle().skip(1))
+    .map(|(a, b)| a.perp_dot(*b))
+    .sum::<f32>()
+    / 2.0
+}
+
+// the outer box with the arena cut out as a single polygon, the hole is joined to the box by a
+// seam from its rightmost corner so the whole thing can be triangulated in one go
+fn killzone_outline(arena: &[Vec2], outer_min: Vec2, outer_max: Vec2) -> Vec<Vec2> {
+  let rightmost = (0..arena.len())
+    .max_by(|a, b| arena[*a].x.total_cmp(&arena[*b].x))
+    .unwrap_or(0);
+  let corner = arena[rightmost];
+  let seam = Vec2::new(outer_max.x, corner.y);
+
+  let mut points = vec![outer_min, Vec2::new(outer_max.x, outer_min.y), seam];
+  // the hole goes clockwise
+  points.extend((0..=arena.len()).map(|n| arena[(rightmost + arena.len() - n) % arena.len()]));
+  points.extend([seam, outer_max, Vec2::new(outer_min.x, outer_max.y)]);
+  points
+}
+
+// ear clipping, fine for the few dozen corners an arena has
+fn triangulate(points: &[Vec2]) -> Vec<[Vec2; 3]> {
+  let mut remaining = points.to_vec();
+  let mut triangles = Vec::new();
+
+  while remaining.len() > 3 {
+    let count = remaining.len();
+    let corners = |n: usize| {
+      (
+        remaining[(n + count - 1) % count],
+        remaining[n],
+        remaining[(n + 1) % count],
+      )
     };
+
+    let ear = (0..count).find(|n| {
+      let (a, b, c) = corners(*n);
+      (b - a).perp_dot(c - b) > 0.0
+        && !remaining
+          .iter()
+          .any(|p| *p != a && *p != b && *p != c && in_triangle(*p, a, b, c))
+    });
+
+    match ear {
+      Some(n) => {
+        let (a, b, c) = corners(n);
+        triangles.push([a, b, c]);
+        remaining.remove(n);
+      }
+      // a partial killzone would let players walk out of the arena unharmed
+      None => panic!(
+        "can't triangulate the killzone, do the arena's edges cross? {:?}",
+        points
+      ),
+    }
+  }
+
+  if let [a, b, c] = remaining[..] {
+    triangles.push([a, b, c]);
+  }
+
+  triangles
+}
+
+fn in_triangle(p: Vec2, a: Vec2, b: Vec2, c: Vec2) -> bool {
+  (b - a).perp_dot(p - a) >= 0.0 && (c - b).perp_dot(p - b) >= 0.0 && (a - c).perp_dot(p - c) >= 0.0
+}
+
+pub fn create_scenery(commands: &mut Commands, scenery: &[Scenery]) {
+  for obstacle in scenery {
+    let (geometry, collider) = match *obstacle {
+      Scenery::Rock { position, radius } => {
+        // an uneven hexagon, the collider uses the same points as the drawing
+        let points: Vec<Vec2> = (0..6)
+          .map(|n| {
+            let angle = n as f32 * std::f32::consts::TAU / 6.0;
+            let jitter = if n % 2 == 0 { 1.0 } else { 0.8 };
+            Vec2::new(angle.cos(), angle.sin()) * radius * jitter
+          })
+          .collect();
+        let collider = Collider::convex_hull(&points).unwrap_or_else(|| Collider::ball(radius));
+        let shape = shapes::Polygon {
+          points,
+          closed: true,
+        };
+        (
+          GeometryBuilder::build_as(
+            &shape,
+            obstacle_draw_mode(Color::rgb(0.45, 0.42, 0.4)),
+            Transform::from_translation(position.extend(0.05)),
+          ),
+          collider,
+        )
+      }
+      Scenery::Pillar { position, radius } => {
+        let shape = shapes::Circle {
+          radius,
+          center: Vec2::ZERO,
+        };
+        (
+          GeometryBuilder::build_as(
+            &shape,
+            obstacle_draw_mode(Color::rgb(0.6, 0.58, 0.55)),
+            Transform::from_translation(position.extend(0.05)),
+          ),
+          Collider::ball(radius),
+        )
+      }
+      Scenery::Wall {
+        from,
+        to,
+        thickness,
+      } => {
+        let length = from.distance(to);
+        let angle = (to - from).y.atan2((to - from).x);
+        let shape = shapes::Rectangle {
+          extents: Vec2::new(length, thickness),
+          origin: RectangleOrigin::Center,
+        };
+        (
+          GeometryBuilder::build_as(
+            &shape,
+            obstacle_draw_mode(Color::rgb(0.4, 0.3, 0.2)),
+            Transform::from_translation(((from + to) / 2.0).extend(0.05))
+              .with_rotation(Quat::from_rotation_z(angle)),
+          ),
+          Collider::cuboid(length / 2.0, thickness / 2.0),
+        )
+      }
+    };
+
     commands.spawn((
       OnGameScreen,
-      Killzone,
-      GeometryBuilder::build_as(
-        &square,
-        DrawMode::Outlined {
-          fill_mode: FillMode::color(Color::rgba(1.0, 0.0, 0.0, 0.3)),
-          outline_mode: StrokeMode::new(Color::rgba(1.0, 0.0, 0.0, 0.0), 0.0),
-        },
-        Transform::from_translation(Vec3::new(translate.0, translate.1, translate.2)),
-      ),
-      Collider::cuboid(cuboid.0, cuboid.1),
-      Sensor,
-      CollidingEntities::default(),
+      Obstacle,
+      geometry,
+      collider,
       RigidBody::Fixed,
-      CollisionGroups::new(Group::GROUP_6, Group::ALL),
+      // players, projectiles, enemies, enemy projectiles and the laser
+      CollisionGroups::new(
+        Group::GROUP_7,
+        Group::GROUP_1
+          .union(Group::GROUP_2)
+          .union(Group::GROUP_3)
+          .union(Group::GROUP_4)
+          .union(Group::GROUP_5),
+      ),
     ));
   }
 }
+
+fn obstacle_draw_mode(color: Color) -> DrawMode {
+  DrawMode::Outlined {
+    fill_mode: FillMode::color(color),
+    outline_mode: StrokeMode::new(Color::rgba(0.0, 0.0, 0.0, 0.5), 2.0),
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::levels::level3::ARENA_CORNERS;
+
+  // an arena with a notch cut into its top edge
+  fn notched() -> Vec<Vec2> {
+    vec![
+      Vec2::new(-400.0, -400.0),
+      Vec2::new(400.0, -400.0),
+      Vec2::new(400.0, 400.0),
+      Vec2::new(100.0, 400.0),
+      Vec2::new(0.0, 0.0),
+      Vec2::new(-100.0, 400.0),
+      Vec2::new(-400.0, 400.0),
+    ]
+  }
+
+  fn polygon(points: Vec<Vec2>) -> Arena {
+    Arena::new(ArenaShape::Polygon { points })
+  }
+
+  fn triangles_area(triangles: &[[Vec2; 3]]) -> f32 {
+    triangles
+      .iter()
+      .map(|[a, b, c]| (*b - *a).perp_dot(*c - *a) / 2.0)
+      .sum()
+  }
+
+  fn assert_covers(points: &[Vec2]) {
+    let triangles = triangulate(points);
+    assert_eq!(triangles.len(), points.len() - 2);
+    // counter clockwise triangles that together fill the polygon exactly
+    assert!(triangles
+      .iter()
+      .all(|[a, b, c]| (*b - *a).perp_dot(*c - *a) > 0.0));
+    // the killzone's area is big enough for f32 rounding to add up
+    let area = signed_area(points);
+    assert!((triangles_area(&triangles) - area).abs() < area * 1e-5);
+  }
+
+  #[test]
+  fn contains_follows_the_shape() {
+    let rectangle = Arena::new(ArenaShape::Rectangle {
+      half_size: Vec2::new(600.0, 300.0),
+    });
+    assert!(rectangle.contains(Vec2::new(-599.0, 299.0)));
+    assert!(!rectangle.contains(Vec2::new(0.0, 301.0)));
+
+    let circle = Arena::new(ArenaShape::Circle { radius: 600.0 });
+    assert!(circle.contains(Vec2::new(400.0, 400.0)));
+    assert!(!circle.contains(Vec2::new(500.0, 500.0)));
+
+    let level3 = polygon(ARENA_CORNERS.to_vec());
+    assert!(level3.contains(Vec2::ZERO));
+    assert!(level3.contains(Vec2::new(550.0, -200.0)));
+    assert!(!level3.contains(Vec2::new(550.0, -500.0)));
+    assert!(!level3.contains(Vec2::new(-500.0, 500.0)));
+
+    let notched = polygon(notched());
+    assert!(notched.contains(Vec2::new(0.0, -100.0)));
+    assert!(notched.contains(Vec2::new(-200.0, 300.0)));
+    assert!(!notched.contains(Vec2::new(0.0, 300.0)));
+  }
+
+  #[test]
+  fn polygon_winding_does_not_matter() {
+    let mut clockwise = ARENA_CORNERS.to_vec();
+    clockwise.reverse();
+    let arena = polygon(clockwise);
+
+    assert!(signed_area(&arena.outline) > 0.0);
+    assert!(arena.contains(Vec2::ZERO));
+    assert!(arena.contains(arena.clamp(Vec2::new(900.0, 0.0), 10.0)));
+  }
+
+  #[test]
+  fn clamp_keeps_a_margin_to_the_edge() {
+    let rectangle = Arena::new(ArenaShape::Rectangle {
+      half_size: Vec2::splat(600.0),
+    });
+    assert_eq!(
+      rectangle.clamp(Vec2::new(700.0, -50.0), 20.0),
+      Vec2::new(580.0, -50.0)
+    );
+    assert_eq!(
+      rectangle.clamp(Vec2::new(10.0, 20.0), 20.0),
+      Vec2::new(10.0, 20.0)
+    );
+
+    let circle = Arena::new(ArenaShape::Circle { radius: 600.0 });
+    assert!((circle.clamp(Vec2::new(0.0, -900.0), 50.0) - Vec2::new(0.0, -550.0)).length() < 0.01);
+
+    let level3 = polygon(ARENA_CORNERS.to_vec());
+    assert_eq!(
+      level3.clamp(Vec2::new(100.0, 50.0), 20.0),
+      Vec2::new(100.0, 50.0)
+    );
+    for point in [
+      Vec2::new(900.0, 0.0),
+      Vec2::new(-700.0, -700.0),
+      Vec2::new(0.0, 590.0),
+    ] {
+      let clamped = level3.clamp(point, 20.0);
+      assert!(level3.contains(clamped), "{:?} clamped outside", point);
+    }
+
+    // a point in the notch ends up on one of its sides
+    let notched = polygon(notched());
+    let clamped = notched.clamp(Vec2::new(0.0, 300.0), 10.0);
+    assert!(notched.contains(clamped));
+  }
+
+  #[test]
+  fn killzone_outline_cuts_the_arena_out_of_the_box() {
+    let arena = polygon(ARENA_CORNERS.to_vec());
+    let (min, max) = arena.bounds();
+    let outer_min = min - KILLZONE_EXTENT;
+    let outer_max = max + KILLZONE_EXTENT;
+    let outline = killzone_outline(&arena.outline, outer_min, outer_max);
+
+    // the box, the seam there and back, and every corner of the arena with the first one twice
+    assert_eq!(outline.len(), ARENA_CORNERS.len() + 7);
+    assert_eq!(outline[2], Vec2::new(outer_max.x, -200.0));
+    assert_eq!(outline[3], Vec2::new(600.0, -200.0));
+    assert!(ARENA_CORNERS.iter().all(|corner| outline.contains(corner)));
+
+    let box_area = (outer_max - outer_min).x * (outer_max - outer_min).y;
+    assert!((signed_area(&outline) - (box_area - signed_area(&arena.outline))).abs() < 1.0);
+  }
+
+  #[test]
+  fn triangulates_arenas_and_killzones() {
+    for arena in [
+      polygon(ARENA_CORNERS.to_vec()),
+      polygon(notched()),
+      Arena::new(ArenaShape::Circle { radius: 600.0 }),
+    ] {
+      assert_covers(&arena.outline);
+
+      let (min, max) = arena.bounds();
+      assert_covers(&killzone_outline(
+        &arena.outline,
+        min - KILLZONE_EXTENT,
+        max + KILLZONE_EXTENT,
+      ));
+    }
+  }
+
+  #[test]
+  #[should_panic]
+  fn outline_without_ears_panics() {
+    // clockwise, so every corner is reflex
+    triangulate(&[
+      Vec2::new(0.0, 0.0),
+      Vec2::new(0.0, 100.0),
+      Vec2::new(100.0, 100.0),
+      Vec2::new(100.0, 0.0),
+    ]);
+  }
+}
@@ -1,5 +1,6 @@
 use bevy::prelude::*;
 
+use crate::levels::endless::EndlessRun;
 use crate::{despawn_screen, GameData, GameState};
 
 pub struct LevelSelectPlugin;
@@ -32,6 +33,7 @@ enum MenuButtonAction {
   Level1,
   Level2,
   Level3,
+  Endless(usize),
   Upgrades,
 }
 
@@ -41,6 +43,7 @@ struct CurrentMoney;
 fn menu_action(
   interaction_query: Query<(&Interaction, &MenuButtonAction), (Changed<Interaction>, With<Button>)>,
   mut game_state: ResMut<State<GameState>>,
+  mut endless_run: ResMut<EndlessRun>,
 ) {
   for (interaction, menu_button_action) in &interaction_query {
     if *interaction == Interaction::Clicked {
@@ -54,6 +57,10 @@ fn menu_action(
         MenuButtonAction::Level3 => {
           game_state.set(GameState::Level3).unwrap();
         }
+        MenuButtonAction::Endless(players) => {
+          endless_run.players = *players;
+          game_state.set(GameState::Endless).unwrap();
+        }
         MenuButtonAction::Upgrades => {
           game_state.set(GameState::Upgrades).unwrap();
         }
@@ -189,5 +196,41 @@ fn setup(mut commands: Commands, asset_server: Res<AssetServer>, data: Res<GameD
               });
           }
         });
+
+      // endless mode unlocks after beating the first level
+      if data.level >= 2 {
+        parent
+          .spawn((
+            NodeBundle {
+              style: Style {
+                align_items: AlignItems::Center,
+                justify_content: JustifyContent::Center,
+                flex_direction: FlexDirection::Row,
+                ..default()
+              },
+              ..default()
+            },
+            OnMenuScreen,
+          ))
+          .with_children(|parent| {
+            for players in 1..=2 {
+              parent
+                .spawn((
+                  ButtonBundle {
+                    style: button_style.clone(),
+                    background_color: NORMAL_BUTTON.into(),
+                    ..default()
+                  },
+                  MenuButtonAction::Endless(players),
+                ))
+                .with_children(|parent| {
+                  parent.spawn(TextBundle::from_section(
+                    format!("Endless {}P", players),
+                    button_text_style.clone(),
+                  ));
+                });
+            }
+          });
+      }
     });
 }
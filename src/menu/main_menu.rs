@@ -31,6 +31,7 @@ const TEXT_COLOR: Color = Color::rgb(0.9, 0.9, 0.9);
 enum MenuButtonAction {
   NewGame,
   Continue,
+  Settings,
 }
 
 fn menu_action(
@@ -50,6 +51,9 @@ fn menu_action(
         MenuButtonAction::Continue => {
           game_state.set(GameState::LevelSelect).unwrap();
         }
+        MenuButtonAction::Settings => {
+          game_state.set(GameState::Settings).unwrap();
+        }
       }
     }
   }
@@ -142,5 +146,21 @@ fn setup(mut commands: Commands, asset_server: Res<AssetServer>, game_data: Res<
             button_text_style.clone(),
           ));
         });
+
+      parent
+        .spawn((
+          ButtonBundle {
+            style: button_style.clone(),
+            background_color: NORMAL_BUTTON.into(),
+            ..default()
+          },
+          MenuButtonAction::Settings,
+        ))
+        .with_children(|parent| {
+          parent.spawn(TextBundle::from_section(
+            "Settings",
+            button_text_style.clone(),
+          ));
+        });
     });
 }
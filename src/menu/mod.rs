@@ -1,3 +1,6 @@
 pub mod level_select;
 pub mod main_menu;
+pub mod pause;
+pub mod results;
+pub mod settings;
 pub mod upgrades;
@@ -0,0 +1,207 @@
+use bevy::app::AppExit;
+use bevy::prelude::*;
+use bevy_pkv::PkvStore;
+use bevy_rapier2d::prelude::*;
+
+use crate::menu::settings;
+use crate::systems;
+use crate::{despawn_screen, GameData, GameState, Settings};
+
+pub struct PausePlugin;
+
+impl Plugin for PausePlugin {
+  fn build(&self, app: &mut App) {
+    app
+      .add_system(toggle_pause)
+      .add_system_set(SystemSet::on_enter(GameState::Paused).with_system(setup))
+      .add_system_set(
+        SystemSet::on_update(GameState::Paused)
+          .with_system(menu_action)
+          .with_system(settings::settings_action)
+          .with_system(button_system),
+      )
+      .add_system_set(
+        SystemSet::on_exit(GameState::Paused)
+          .with_system(despawn_screen::<OnMenuScreen>)
+          .with_system(resume_physics),
+      );
+  }
+}
+
+#[derive(Component)]
+struct OnMenuScreen;
+
+const HOVERED_BUTTON: Color = Color::rgb(0.15, 0.82, 0.2);
+const NORMAL_BUTTON: Color = Color::rgb(0.20, 0.62, 0.27);
+
+const TEXT_COLOR: Color = Color::rgb(0.9, 0.9, 0.9);
+
+#[derive(Component)]
+enum MenuButtonAction {
+  Resume,
+  Restart,
+  LevelSelect,
+  Quit,
+}
+
+// pushing the paused state keeps the level entities alive while its systems stop running
+fn toggle_pause(
+  keyboard: Res<Input<KeyCode>>,
+  mut game_state: ResMut<State<GameState>>,
+  mut rapier_config: ResMut<RapierConfiguration>,
+) {
+  if !keyboard.just_pressed(KeyCode::Escape) {
+    return;
+  }
+
+  if game_state.current().is_level() {
+    if game_state.push(GameState::Paused).is_ok() {
+      rapier_config.physics_pipeline_active = false;
+    }
+  } else if *game_state.current() == GameState::Paused {
+    let _ = game_state.pop();
+  }
+}
+
+fn resume_physics(mut rapier_config: ResMut<RapierConfiguration>) {
+  rapier_config.physics_pipeline_active = true;
+}
+
+fn menu_action(
+  interaction_query: Query<(&Interaction, &MenuButtonAction), (Changed<Interaction>, With<Button>)>,
+  mut game_state: ResMut<State<GameState>>,
+  mut data: ResMut<GameData>,
+  mut pkv: ResMut<PkvStore>,
+  mut exit: EventWriter<AppExit>,
+) {
+  // escape and a click can land in the same frame, only the first transition queued goes through
+  for (interaction, menu_button_action) in &interaction_query {
+    if *interaction == Interaction::Clicked {
+      match menu_button_action {
+        MenuButtonAction::Resume => {
+          let _ = game_state.pop();
+        }
+        MenuButtonAction::Restart => {
+          // replacing the stack exits the paused level and enters it again
+          if let Some(level) = game_state.inactives().last().cloned() {
+            let _ = game_state.replace(level);
+          }
+        }
+        MenuButtonAction::LevelSelect => {
+          // the level's exit systems save the game
+          let _ = game_state.replace(GameState::LevelSelect);
+        }
+        MenuButtonAction::Quit => {
+          systems::write_save(&mut data, &mut pkv);
+          exit.send(AppExit);
+        }
+      }
+    }
+  }
+}
+
+fn button_system(
+  mut interaction_query: Query<
+    (&Interaction, &mut BackgroundColor),
+    (Changed<Interaction>, With<Button>),
+  >,
+) {
+  for (interaction, mut color) in &mut interaction_query {
+    *color = match *interaction {
+      Interaction::Hovered | Interaction::Clicked => HOVERED_BUTTON.into(),
+      Interaction::None => NORMAL_BUTTON.into(),
+    }
+  }
+}
+
+fn setup(mut commands: Commands, asset_server: Res<AssetServer>, settings: Res<Settings>) {
+  let font = asset_server.load("font.ttf");
+  let button_text_style = TextStyle {
+    font: font.clone(),
+    font_size: 40.0,
+    color: TEXT_COLOR,
+  };
+  let button_style = Style {
+    size: Size::new(Val::Px(450.0), Val::Px(65.0)),
+    margin: UiRect::all(Val::Px(10.0)),
+    justify_content: JustifyContent::Center,
+    align_items: AlignItems::Center,
+    ..default()
+  };
+
+  commands
+    .spawn((
+      NodeBundle {
+        style: Style {
+          position_type: PositionType::Absolute,
+          align_items: AlignItems::Center,
+          justify_content: JustifyContent::Center,
+          flex_direction: FlexDirection::Column,
+          size: Size::new(Val::Percent(100.0), Val::Percent(100.0)),
+          ..default()
+        },
+        background_color: Color::rgba(0.0, 0.0, 0.0, 0.6).into(),
+        ..default()
+      },
+      OnMenuScreen,
+    ))
+    .with_children(|parent| {
+      parent.spawn(
+        TextBundle::from_section(
+          "Paused",
+          TextStyle {
+            font: font.clone(),
+            font_size: 60.0,
+            color: TEXT_COLOR,
+          },
+        )
+        .with_style(Style {
+          margin: UiRect::all(Val::Px(30.0)),
+          ..default()
+        }),
+      );
+
+      for (action, label) in [
+        (MenuButtonAction::Resume, "Resume"),
+        (MenuButtonAction::Restart, "Restart level"),
+      ] {
+        parent
+          .spawn((
+            ButtonBundle {
+              style: button_style.clone(),
+              background_color: NORMAL_BUTTON.into(),
+              ..default()
+            },
+            action,
+          ))
+          .with_children(|parent| {
+            parent.spawn(TextBundle::from_section(label, button_text_style.clone()));
+          });
+      }
+
+      settings::spawn_settings_buttons(
+        parent,
+        &settings,
+        button_style.clone(),
+        button_text_style.clone(),
+      );
+
+      for (action, label) in [
+        (MenuButtonAction::LevelSelect, "Abandon level"),
+        (MenuButtonAction::Quit, "Quit game"),
+      ] {
+        parent
+          .spawn((
+            ButtonBundle {
+              style: button_style.clone(),
+              background_color: NORMAL_BUTTON.into(),
+              ..default()
+            },
+            action,
+          ))
+          .with_children(|parent| {
+            parent.spawn(TextBundle::from_section(label, button_text_style.clone()));
+          });
+      }
+    });
+}
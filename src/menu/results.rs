@@ -0,0 +1,220 @@
+use bevy::prelude::*;
+
+use crate::enemy::EnemyKind;
+use crate::stats::RunStats;
+use crate::{despawn_screen, DamageSource, GameState};
+
+pub struct ResultsPlugin;
+
+impl Plugin for ResultsPlugin {
+  fn build(&self, app: &mut App) {
+    app
+      .add_system_set(SystemSet::on_enter(GameState::Results).with_system(setup))
+      .add_system_set(
+        SystemSet::on_update(GameState::Results)
+          .with_system(menu_action)
+          .with_system(button_system),
+      )
+      .add_system_set(
+        SystemSet::on_exit(GameState::Results).with_system(despawn_screen::<OnMenuScreen>),
+      );
+  }
+}
+
+#[derive(Component)]
+struct OnMenuScreen;
+
+const HOVERED_BUTTON: Color = Color::rgb(0.15, 0.82, 0.2);
+const NORMAL_BUTTON: Color = Color::rgb(0.20, 0.62, 0.27);
+
+const TEXT_COLOR: Color = Color::rgb(0.9, 0.9, 0.9);
+const VICTORY_COLOR: Color = Color::rgb(0.15, 0.82, 0.2);
+const DEFEAT_COLOR: Color = Color::rgb(0.82, 0.15, 0.15);
+
+#[derive(Component)]
+enum MenuButtonAction {
+  Retry,
+  Continue,
+}
+
+fn menu_action(
+  interaction_query: Query<(&Interaction, &MenuButtonAction), (Changed<Interaction>, With<Button>)>,
+  mut game_state: ResMut<State<GameState>>,
+  stats: Res<RunStats>,
+) {
+  for (interaction, menu_button_action) in &interaction_query {
+    if *interaction == Interaction::Clicked {
+      match menu_button_action {
+        MenuButtonAction::Retry => {
+          if let Some(level) = &stats.level {
+            game_state.set(level.clone()).unwrap();
+          }
+        }
+        MenuButtonAction::Continue => {
+          game_state.set(GameState::LevelSelect).unwrap();
+        }
+      }
+    }
+  }
+}
+
+fn button_system(
+  mut interaction_query: Query<
+    (&Interaction, &mut BackgroundColor),
+    (Changed<Interaction>, With<Button>),
+  >,
+) {
+  for (interaction, mut color) in &mut interaction_query {
+    *color = match *interaction {
+      Interaction::Hovered | Interaction::Clicked => HOVERED_BUTTON.into(),
+      Interaction::None => NORMAL_BUTTON.into(),
+    }
+  }
+}
+
+fn stat_lines(stats: &RunStats) -> Vec<String> {
+  let mut lines = vec![format!(
+    "Time survived {}:{:02}",
+    stats.time as u32 / 60,
+    stats.time as u32 % 60
+  )];
+
+  if let Some(waves) = stats.waves_survived {
+    lines.push(format!(
+      "Waves survived {} (best {})",
+      waves, stats.best_waves
+    ));
+  }
+
+  for kind in [
+    EnemyKind::Slime,
+    EnemyKind::Elite,
+    EnemyKind::Boss,
+    EnemyKind::BossSpawn,
+    EnemyKind::Ranged,
+    EnemyKind::Splitter,
+    EnemyKind::Healer,
+    EnemyKind::ShieldBearer,
+  ] {
+    if let Some(kills) = stats.kills.get(&kind) {
+      lines.push(format!("{} killed: {}", kind.name(), kills));
+    }
+  }
+
+  for source in [
+    DamageSource::Gun,
+    DamageSource::Lightning,
+    DamageSource::Laser,
+    DamageSource::Killzone,
+    DamageSource::Hazard,
+  ] {
+    if let Some(damage) = stats.damage.get(&source) {
+      lines.push(format!("{} damage: {:.0}", source.name(), damage));
+    }
+  }
+
+  lines.push(format!("Money earned ${}", stats.money_earned));
+
+  if let Some(level) = stats.unlock {
+    lines.push(format!("Level {} unlocked!", level));
+  }
+
+  lines
+}
+
+fn setup(mut commands: Commands, asset_server: Res<AssetServer>, stats: Res<RunStats>) {
+  let font = asset_server.load("font.ttf");
+  let button_text_style = TextStyle {
+    font: font.clone(),
+    font_size: 40.0,
+    color: TEXT_COLOR,
+  };
+  let stat_text_style = TextStyle {
+    font: font.clone(),
+    font_size: 28.0,
+    color: TEXT_COLOR,
+  };
+  let button_style = Style {
+    size: Size::new(Val::Px(250.0), Val::Px(65.0)),
+    margin: UiRect::all(Val::Px(20.0)),
+    justify_content: JustifyContent::Center,
+    align_items: AlignItems::Center,
+    ..default()
+  };
+
+  commands
+    .spawn((
+      NodeBundle {
+        style: Style {
+          align_items: AlignItems::Center,
+          justify_content: JustifyContent::Center,
+          flex_direction: FlexDirection::Column,
+          size: Size::new(Val::Percent(100.0), Val::Percent(100.0)),
+          ..default()
+        },
+        ..default()
+      },
+      OnMenuScreen,
+    ))
+    .with_children(|parent| {
+      parent.spawn(
+        TextBundle::from_section(
+          if stats.won {
+            "Victory!"
+          } else if stats.waves_survived.is_some() {
+            "Game over"
+          } else {
+            "Defeat"
+          },
+          TextStyle {
+            font: font.clone(),
+            font_size: 60.0,
+            color: if stats.won {
+              VICTORY_COLOR
+            } else {
+              DEFEAT_COLOR
+            },
+          },
+        )
+        .with_style(Style {
+          margin: UiRect::all(Val::Px(30.0)),
+          ..default()
+        }),
+      );
+
+      for line in stat_lines(&stats) {
+        parent.spawn(TextBundle::from_section(line, stat_text_style.clone()));
+      }
+
+      parent
+        .spawn(NodeBundle {
+          style: Style {
+            align_items: AlignItems::Center,
+            justify_content: JustifyContent::Center,
+            flex_direction: FlexDirection::Row,
+            margin: UiRect::all(Val::Px(30.0)),
+            ..default()
+          },
+          ..default()
+        })
+        .with_children(|parent| {
+          for (action, label) in [
+            (MenuButtonAction::Retry, "Retry"),
+            (MenuButtonAction::Continue, "Continue"),
+          ] {
+            parent
+              .spawn((
+                ButtonBundle {
+                  style: button_style.clone(),
+                  background_color: NORMAL_BUTTON.into(),
+                  ..default()
+                },
+                action,
+              ))
+              .with_children(|parent| {
+                parent.spawn(TextBundle::from_section(label, button_text_style.clone()));
+              });
+          }
+        });
+    });
+}
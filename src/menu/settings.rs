@@ -0,0 +1,184 @@
+use bevy::prelude::*;
+use bevy_pkv::PkvStore;
+
+use crate::systems;
+use crate::{despawn_screen, GameState, Settings};
+
+pub struct SettingsPlugin;
+
+impl Plugin for SettingsPlugin {
+  fn build(&self, app: &mut App) {
+    app
+      .add_system_set(SystemSet::on_enter(GameState::Settings).with_system(setup))
+      .add_system_set(
+        SystemSet::on_update(GameState::Settings)
+          .with_system(menu_action)
+          .with_system(settings_action)
+          .with_system(button_system),
+      )
+      .add_system_set(
+        SystemSet::on_exit(GameState::Settings).with_system(despawn_screen::<OnMenuScreen>),
+      );
+  }
+}
+
+#[derive(Component)]
+struct OnMenuScreen;
+
+const HOVERED_BUTTON: Color = Color::rgb(0.15, 0.82, 0.2);
+const NORMAL_BUTTON: Color = Color::rgb(0.20, 0.62, 0.27);
+
+const TEXT_COLOR: Color = Color::rgb(0.9, 0.9, 0.9);
+
+#[derive(Component)]
+enum MenuButtonAction {
+  MainMenu,
+}
+
+// shared with the pause menu so settings can be changed mid level
+#[derive(Component)]
+pub enum SettingsButtonAction {
+  ToggleDamageNumbers,
+}
+
+#[derive(Component)]
+pub struct DamageNumbersLabel;
+
+fn damage_numbers_label(settings: &Settings) -> String {
+  if settings.damage_numbers {
+    "Damage numbers: On".into()
+  } else {
+    "Damage numbers: Off".into()
+  }
+}
+
+pub fn spawn_settings_buttons(
+  parent: &mut ChildBuilder,
+  settings: &Settings,
+  button_style: Style,
+  button_text_style: TextStyle,
+) {
+  parent
+    .spawn((
+      ButtonBundle {
+        style: Style {
+          size: Size::new(Val::Px(450.0), Val::Px(65.0)),
+          ..button_style
+        },
+        background_color: NORMAL_BUTTON.into(),
+        ..default()
+      },
+      SettingsButtonAction::ToggleDamageNumbers,
+    ))
+    .with_children(|parent| {
+      parent.spawn((
+        DamageNumbersLabel,
+        TextBundle::from_section(damage_numbers_label(settings), button_text_style),
+      ));
+    });
+}
+
+pub fn settings_action(
+  interaction_query: Query<
+    (&Interaction, &SettingsButtonAction),
+    (Changed<Interaction>, With<Button>),
+  >,
+  mut settings: ResMut<Settings>,
+  mut pkv: ResMut<PkvStore>,
+  mut labels: Query<&mut Text, With<DamageNumbersLabel>>,
+) {
+  for (interaction, settings_button_action) in &interaction_query {
+    if *interaction == Interaction::Clicked {
+      match settings_button_action {
+        SettingsButtonAction::ToggleDamageNumbers => {
+          settings.damage_numbers = !settings.damage_numbers;
+          for mut label in labels.iter_mut() {
+            label.sections[0].value = damage_numbers_label(&settings);
+          }
+        }
+      }
+      systems::save_settings(&settings, &mut pkv);
+    }
+  }
+}
+
+fn menu_action(
+  interaction_query: Query<(&Interaction, &MenuButtonAction), (Changed<Interaction>, With<Button>)>,
+  mut game_state: ResMut<State<GameState>>,
+) {
+  for (interaction, menu_button_action) in &interaction_query {
+    if *interaction == Interaction::Clicked {
+      match menu_button_action {
+        MenuButtonAction::MainMenu => {
+          game_state.set(GameState::MainMenu).unwrap();
+        }
+      }
+    }
+  }
+}
+
+fn button_system(
+  mut interaction_query: Query<
+    (&Interaction, &mut BackgroundColor),
+    (Changed<Interaction>, With<Button>),
+  >,
+) {
+  for (interaction, mut color) in &mut interaction_query {
+    *color = match *interaction {
+      Interaction::Hovered | Interaction::Clicked => HOVERED_BUTTON.into(),
+      Interaction::None => NORMAL_BUTTON.into(),
+    }
+  }
+}
+
+fn setup(mut commands: Commands, asset_server: Res<AssetServer>, settings: Res<Settings>) {
+  let font = asset_server.load("font.ttf");
+  let button_text_style = TextStyle {
+    font: font.clone(),
+    font_size: 40.0,
+    color: TEXT_COLOR,
+  };
+  let button_style = Style {
+    size: Size::new(Val::Px(250.0), Val::Px(65.0)),
+    margin: UiRect::all(Val::Px(20.0)),
+    justify_content: JustifyContent::Center,
+    align_items: AlignItems::Center,
+    ..default()
+  };
+
+  commands
+    .spawn((
+      NodeBundle {
+        style: Style {
+          align_items: AlignItems::Center,
+          justify_content: JustifyContent::Center,
+          flex_direction: FlexDirection::Column,
+          size: Size::new(Val::Percent(100.0), Val::Percent(100.0)),
+          ..default()
+        },
+        ..default()
+      },
+      OnMenuScreen,
+    ))
+    .with_children(|parent| {
+      spawn_settings_buttons(
+        parent,
+        &settings,
+        button_style.clone(),
+        button_text_style.clone(),
+      );
+
+      parent
+        .spawn((
+          ButtonBundle {
+            style: button_style.clone(),
+            background_color: NORMAL_BUTTON.into(),
+            ..default()
+          },
+          MenuButtonAction::MainMenu,
+        ))
+        .with_children(|parent| {
+          parent.spawn(TextBundle::from_section("Back", button_text_style.clone()));
+        });
+    });
+}
@@ -35,6 +35,9 @@ enum MenuButtonAction {
   LaserIncreaseDamage,
   LevelSelect,
   PlayerMsIncrease,
+  MagnetIncrease,
+  CritChanceIncrease,
+  CritMultiplierIncrease,
 }
 
 #[derive(Component)]
@@ -94,6 +97,33 @@ fn menu_action(
             }
           }
         }
+        MenuButtonAction::MagnetIncrease => {
+          if data.money >= 100 {
+            data.magnet_radius += 25.0;
+            data.money -= 100;
+            for mut display in money_display.iter_mut() {
+              display.sections[0].value = format!("Available money ${:?}", data.money);
+            }
+          }
+        }
+        MenuButtonAction::CritChanceIncrease => {
+          if data.money >= 100 && data.crit_chance < 1.0 {
+            data.crit_chance = (data.crit_chance + 0.05).min(1.0);
+            data.money -= 100;
+            for mut display in money_display.iter_mut() {
+              display.sections[0].value = format!("Available money ${:?}", data.money);
+            }
+          }
+        }
+        MenuButtonAction::CritMultiplierIncrease => {
+          if data.money >= 100 {
+            data.crit_multiplier += 0.25;
+            data.money -= 100;
+            for mut display in money_display.iter_mut() {
+              display.sections[0].value = format!("Available money ${:?}", data.money);
+            }
+          }
+        }
         MenuButtonAction::LevelSelect => {
           game_state.set(GameState::LevelSelect).unwrap();
         }
@@ -379,6 +409,54 @@ fn setup(mut commands: Commands, asset_server: Res<AssetServer>, state: Res<Game
                     button_text_style.clone(),
                   ));
                 });
+
+              parent
+                .spawn((
+                  ButtonBundle {
+                    style: button_style.clone(),
+                    background_color: NORMAL_BUTTON.into(),
+                    ..default()
+                  },
+                  MenuButtonAction::MagnetIncrease,
+                ))
+                .with_children(|parent| {
+                  parent.spawn(TextBundle::from_section(
+                    "Magnet $100",
+                    button_text_style.clone(),
+                  ));
+                });
+
+              parent
+                .spawn((
+                  ButtonBundle {
+                    style: button_style.clone(),
+                    background_color: NORMAL_BUTTON.into(),
+                    ..default()
+                  },
+                  MenuButtonAction::CritChanceIncrease,
+                ))
+                .with_children(|parent| {
+                  parent.spawn(TextBundle::from_section(
+                    "Crit chance $100",
+                    button_text_style.clone(),
+                  ));
+                });
+
+              parent
+                .spawn((
+                  ButtonBundle {
+                    style: button_style.clone(),
+                    background_color: NORMAL_BUTTON.into(),
+                    ..default()
+                  },
+                  MenuButtonAction::CritMultiplierIncrease,
+                ))
+                .with_children(|parent| {
+                  parent.spawn(TextBundle::from_section(
+                    "Crit damage $100",
+                    button_text_style.clone(),
+                  ));
+                });
             });
         });
     });
@@ -0,0 +1,244 @@
+use crate::components::*;
+use crate::hazard::Hazard;
+use crate::map::Arena;
+use crate::player::Player;
+use crate::props::Prop;
+use bevy::prelude::*;
+use bevy::utils::HashMap;
+use bevy_rapier2d::prelude::*;
+use std::cmp::Reverse;
+use std::collections::BinaryHeap;
+
+const CELL_SIZE: f32 = 25.0;
+// cells closer than this to anything enemies avoid are blocked, about the size of a slime
+const CLEARANCE: f32 = 12.0;
+const UNREACHABLE: u32 = u32::MAX;
+
+// the eight neighbours of a cell and what it costs to step there
+const NEIGHBOURS: [(IVec2, u32); 8] = [
+  (IVec2::new(1, 0), 10),
+  (IVec2::new(-1, 0), 10),
+  (IVec2::new(0, 1), 10),
+  (IVec2::new(0, -1), 10),
+  (IVec2::new(1, 1), 14),
+  (IVec2::new(1, -1), 14),
+  (IVec2::new(-1, 1), 14),
+  (IVec2::new(-1, -1), 14),
+];
+
+// which cells of the arena can be walked through, built from the static colliders
+pub struct NavGrid {
+  origin: Vec2,
+  size: IVec2,
+  blocked: Vec<bool>,
+}
+
+impl NavGrid {
+  fn build(
+    arena: &Arena,
+    rapier_context: &RapierContext,
+    entities: &Query<()>,
+    hazards: &Query<&Hazard>,
+  ) -> Self {
+    let (min, max) = arena.bounds();
+    let size = ((max - min) / CELL_SIZE).ceil().as_ivec2();
+    let mut grid = NavGrid {
+      origin: min,
+      size,
+      blocked: vec![false; (size.x * size.y) as usize],
+    };
+
+    let solid = Collider::ball(CLEARANCE);
+    // obstacles, props, killzones and damaging zones, the zones are sensors so those are included
+    // too, destroyed props are only removed from physics at the end of the frame
+    let blocks = |entity| {
+      entities.contains(entity) && hazards.get(entity).map_or(true, Hazard::blocks_enemies)
+    };
+    let filter = QueryFilter::new()
+      .groups(
+        CollisionGroups::new(
+          Group::GROUP_3,
+          Group::GROUP_6
+            .union(Group::GROUP_7)
+            .union(Group::GROUP_8)
+            .union(Group::GROUP_10),
+        )
+        .into(),
+      )
+      .predicate(&blocks);
+
+    for y in 0..size.y {
+      for x in 0..size.x {
+        let cell = IVec2::new(x, y);
+        let blocked = rapier_context
+          .intersection_with_shape(grid.center(cell), 0.0, &solid, filter)
+          .is_some();
+        grid.blocked[(y * size.x + x) as usize] = blocked;
+      }
+    }
+
+    grid
+  }
+
+  fn cell(&self, position: Vec2) -> IVec2 {
+    ((position - self.origin) / CELL_SIZE).floor().as_ivec2()
+  }
+
+  fn center(&self, cell: IVec2) -> Vec2 {
+    (cell.as_vec2() + 0.5) * CELL_SIZE + self.origin
+  }
+
+  fn index(&self, cell: IVec2) -> Option<usize> {
+    let inside = cell.x >= 0 && cell.y >= 0 && cell.x < self.size.x && cell.y < self.size.y;
+    inside.then(|| (cell.y * self.size.x + cell.x) as usize)
+  }
+
+  fn is_walkable(&self, cell: IVec2) -> bool {
+    self.index(cell).map_or(false, |index| !self.blocked[index])
+  }
+
+  // diagonal steps may not cut the corner of a blocked cell
+  fn can_step(&self, from: IVec2, offset: IVec2) -> bool {
+    self.is_walkable(from + offset)
+      && (offset.x == 0
+        || offset.y == 0
+        || (self.is_walkable(from + IVec2::new(offset.x, 0))
+          && self.is_walkable(from + IVec2::new(0, offset.y))))
+  }
+}
+
+// distance of every cell to the cell a player stands on
+struct FlowField {
+  target: IVec2,
+  distance: Vec<u32>,
+}
+
+impl FlowField {
+  fn build(grid: &NavGrid, target: IVec2) -> Self {
+    let mut distance = vec![UNREACHABLE; grid.blocked.len()];
+    let mut open = BinaryHeap::new();
+
+    if let Some(index) = grid.index(target) {
+      distance[index] = 0;
+      open.push(Reverse((0, target.x, target.y)));
+    }
+
+    while let Some(Reverse((cost, x, y))) = open.pop() {
+      let cell = IVec2::new(x, y);
+      if cost > distance[grid.index(cell).unwrap()] {
+        continue;
+      }
+
+      for (offset, step) in NEIGHBOURS {
+        // steps are symmetric, so walking outwards from the target gives the distance towards it
+        if !grid.can_step(cell, offset) {
+          continue;
+        }
+        let next = cell + offset;
+        let index = grid.index(next).unwrap();
+        if cost + step < distance[index] {
+          distance[index] = cost + step;
+          open.push(Reverse((cost + step, next.x, next.y)));
+        }
+      }
+    }
+
+    FlowField { target, distance }
+  }
+
+  fn distance(&self, grid: &NavGrid, cell: IVec2) -> u32 {
+    grid
+      .index(cell)
+      .map_or(UNREACHABLE, |index| self.distance[index])
+  }
+}
+
+// paths around obstacles towards every player, enemies ask for the direction to walk in
+#[derive(Resource, Default)]
+pub struct Navigation {
+  grid: Option<NavGrid>,
+  fields: HashMap<Entity, FlowField>,
+  stale: bool,
+}
+
+impl Navigation {
+  // rebuilds the grid next step, for changes the nav systems can't see themselves
+  pub fn invalidate(&mut self) {
+    self.stale = true;
+  }
+
+  // where to go from `position` to reach the closest player the shortest way, None when the
+  // player can't be reached or is in the same cell, then walking straight at it is best
+  pub fn direction(&self, position: Vec2) -> Option<Vec2> {
+    let grid = self.grid.as_ref()?;
+    let cell = grid.cell(position);
+
+    let field = self
+      .fields
+      .values()
+      .min_by_key(|field| field.distance(grid, cell))?;
+    let here = field.distance(grid, cell);
+    if here == 0 {
+      return None;
+    }
+
+    // enemies pushed into a blocked cell walk back out towards the nearest reachable one
+    let next = NEIGHBOURS
+      .iter()
+      .filter(|(offset, _)| here == UNREACHABLE || grid.can_step(cell, *offset))
+      .map(|(offset, _)| cell + *offset)
+      .filter(|next| field.distance(grid, *next) < here)
+      .min_by_key(|next| field.distance(grid, *next))?;
+
+    Some((grid.center(next) - position).normalize_or_zero())
+  }
+}
+
+// the grid only changes when obstacles, props, killzones or hazards come and go, broken props
+// invalidate it
+pub fn update_nav_grid(
+  added: Query<(), Or<(Added<Obstacle>, Added<Prop>, Added<Killzone>, Added<Hazard>)>>,
+  removed: RemovedComponents<Obstacle>,
+  entities: Query<()>,
+  hazards: Query<&Hazard>,
+  arena: Res<Arena>,
+  rapier_context: Res<RapierContext>,
+  mut navigation: ResMut<Navigation>,
+) {
+  if added.is_empty() && removed.iter().next().is_none() && !navigation.stale {
+    return;
+  }
+
+  navigation.grid = Some(NavGrid::build(&arena, &rapier_context, &entities, &hazards));
+  navigation.fields.clear();
+  navigation.stale = false;
+}
+
+// a field is only rebuilt once its player walked into another cell
+pub fn update_flow_fields(
+  players: Query<(Entity, &Transform), With<Player>>,
+  mut navigation: ResMut<Navigation>,
+) {
+  let navigation = navigation.as_mut();
+  let grid = match &navigation.grid {
+    Some(grid) => grid,
+    None => return,
+  };
+
+  navigation
+    .fields
+    .retain(|entity, _| players.contains(*entity));
+
+  for (entity, transform) in players.iter() {
+    let target = grid.cell(transform.translation.truncate());
+    if navigation
+      .fields
+      .get(&entity)
+      .map_or(true, |field| field.target != target)
+    {
+      navigation
+        .fields
+        .insert(entity, FlowField::build(grid, target));
+    }
+  }
+}
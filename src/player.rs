@@ -1,4 +1,7 @@
+use crate::coins::Magnet;
 use crate::components::*;
+use crate::fixed::Interpolated;
+use crate::props::PowerUps;
 use crate::weapons::gun::*;
 use crate::weapons::laser::*;
 use crate::weapons::lightning::*;
@@ -7,12 +10,16 @@ use bevy::prelude::*;
 use bevy_rapier2d::prelude::*;
 use leafwing_input_manager::prelude::*;
 
-#[derive(Component, Clone, Copy)]
+#[derive(Component, Clone, Copy, PartialEq, Eq, Debug)]
 pub enum Player {
   One,
   Two,
 }
 
+// attack presses are read every frame but used by the next simulation step, so none are missed
+#[derive(Component, Default)]
+pub struct AttackBuffer(pub bool);
+
 #[derive(Component)]
 pub struct Movement {
   speed: f32,
@@ -26,6 +33,11 @@ pub fn create_player(
   asset_server: &Res<AssetServer>,
   data: &GameData,
 ) {
+  let transform = match player {
+    Player::One => Transform::from_translation(Vec3::new(0.0, -20.0, 0.0)),
+    Player::Two => Transform::from_translation(Vec3::new(0.0, 20.0, 0.0)),
+  };
+
   commands
     .spawn((
       OnGameScreen,
@@ -36,16 +48,27 @@ pub fn create_player(
       Gun {
         cooldown: Timer::from_seconds(data.gun_cooldown, TimerMode::Repeating),
         damage: data.gun_damage,
+        knockback: data.gun_knockback,
+      },
+      Critical {
+        chance: data.crit_chance,
+        multiplier: data.crit_multiplier,
       },
       Health::new(50.0),
       ActiveEvents::COLLISION_EVENTS,
-      CollisionGroups::new(Group::GROUP_1, Group::GROUP_3.union(Group::GROUP_6)),
+      CollisionGroups::new(
+        Group::GROUP_1,
+        Group::GROUP_3
+          .union(Group::GROUP_4)
+          .union(Group::GROUP_6)
+          .union(Group::GROUP_7)
+          .union(Group::GROUP_8)
+          .union(Group::GROUP_9)
+          .union(Group::GROUP_10),
+      ),
       SpriteSheetBundle {
         texture_atlas,
-        transform: match player {
-          Player::One => Transform::from_translation(Vec3::new(0.0, -20.0, 0.0)),
-          Player::Two => Transform::from_translation(Vec3::new(0.0, 20.0, 0.0)),
-        },
+        transform,
         ..default()
       },
       AnimationTimer(Timer::from_seconds(0.3, TimerMode::Repeating)),
@@ -57,7 +80,16 @@ pub fn create_player(
       RigidBody::Dynamic,
     ))
     .insert((Collider::ball(12.), Restitution::coefficient(0.7)))
-    .insert(input_manager)
+    .insert((
+      input_manager,
+      AttackBuffer::default(),
+      SpeedMultiplier::default(),
+      PowerUps::default(),
+      Magnet {
+        radius: data.magnet_radius,
+      },
+      Interpolated::new(transform.translation),
+    ))
     .with_children(|parent| {
       match player {
         Player::One => {
@@ -97,16 +129,34 @@ pub fn create_player(
 }
 
 pub fn player_movement(
-  mut player_query: Query<(&mut Velocity, &ActionState<Action>, &Movement), With<Player>>,
+  mut player_query: Query<
+    (
+      &mut Velocity,
+      &ActionState<Action>,
+      &Movement,
+      &SpeedMultiplier,
+      &PowerUps,
+    ),
+    With<Player>,
+  >,
 ) {
-  for (mut velocity, action_state, movement) in player_query.iter_mut() {
+  for (mut velocity, action_state, movement, multiplier, power_ups) in player_query.iter_mut() {
     if action_state.pressed(Action::Move) {
       let mx_vec = action_state.clamped_axis_pair(Action::Move).unwrap().xy();
-      velocity.linvel.x = mx_vec.x * movement.speed;
-      velocity.linvel.y = mx_vec.y * movement.speed;
+      let speed = movement.speed * multiplier.0 * power_ups.speed_multiplier();
+      velocity.linvel.x = mx_vec.x * speed;
+      velocity.linvel.y = mx_vec.y * speed;
     } else {
       velocity.linvel.x = 0.0;
       velocity.linvel.y = 0.0;
     }
   }
 }
+
+pub fn buffer_attack_input(mut player_query: Query<(&ActionState<Action>, &mut AttackBuffer)>) {
+  for (action_state, mut buffer) in player_query.iter_mut() {
+    if action_state.just_pressed(Action::Attack) {
+      buffer.0 = true;
+    }
+  }
+}
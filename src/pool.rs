@@ -0,0 +1,68 @@
+use bevy::prelude::*;
+use bevy::utils::HashMap;
+use bevy_rapier2d::prelude::*;
+
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub enum PoolKind {
+  Projectile,
+  LightningFlash,
+  DamageText,
+  Coin,
+}
+
+// entities with this component are disabled and recycled instead of despawned
+#[derive(Component, Debug)]
+pub struct Pooled {
+  pub kind: PoolKind,
+  pub active: bool,
+}
+
+impl Pooled {
+  pub fn new(kind: PoolKind) -> Self {
+    Pooled { kind, active: true }
+  }
+}
+
+#[derive(Resource, Default)]
+pub struct EntityPools {
+  free: HashMap<PoolKind, Vec<Entity>>,
+}
+
+impl EntityPools {
+  // a disabled entity of the given kind, the caller inserts whatever changes between uses
+  pub fn acquire(&mut self, kind: PoolKind, pooled: &mut Query<&mut Pooled>) -> Option<Entity> {
+    let free = self.free.entry(kind).or_default();
+
+    // entities left over from a previous level are gone by now and simply dropped
+    while let Some(entity) = free.pop() {
+      if let Ok(mut pooled) = pooled.get_mut(entity) {
+        if !pooled.active {
+          pooled.active = true;
+          return Some(entity);
+        }
+      }
+    }
+
+    None
+  }
+
+  pub fn release(&mut self, commands: &mut Commands, entity: Entity, pooled: &mut Pooled) {
+    // an entity can be asked to despawn more than once in the same frame
+    if !pooled.active {
+      return;
+    }
+    pooled.active = false;
+
+    let mut entity_commands = commands.entity(entity);
+    entity_commands.insert(Visibility::INVISIBLE);
+    if pooled.kind == PoolKind::Projectile {
+      entity_commands.insert((
+        RigidBody::Fixed,
+        Velocity::zero(),
+        CollisionGroups::new(Group::NONE, Group::NONE),
+      ));
+    }
+
+    self.free.entry(pooled.kind).or_default().push(entity);
+  }
+}
@@ -0,0 +1,251 @@
+use crate::coins::drop_coins;
+use crate::components::*;
+use crate::fixed::{GameRng, GameTime};
+use crate::navigation::Navigation;
+use crate::player::Player;
+use crate::pool::{EntityPools, Pooled};
+use crate::{DespawnEvent, TextureAtlasHandles};
+use bevy::prelude::*;
+use bevy::utils::HashSet;
+use bevy_prototype_lyon::prelude::FillMode;
+use bevy_prototype_lyon::prelude::*;
+use bevy_rapier2d::prelude::*;
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum PropKind {
+  Crate,
+  Barrel,
+}
+
+impl PropKind {
+  fn sprite_index(&self) -> usize {
+    match self {
+      PropKind::Crate => 0,
+      PropKind::Barrel => 1,
+    }
+  }
+
+  fn health(&self) -> f32 {
+    match self {
+      PropKind::Crate => 60.0,
+      PropKind::Barrel => 30.0,
+    }
+  }
+
+  fn collider(&self) -> Collider {
+    match self {
+      PropKind::Crate => Collider::cuboid(13.0, 13.0),
+      PropKind::Barrel => Collider::cuboid(11.0, 14.0),
+    }
+  }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub enum PowerUpKind {
+  // faster movement
+  Haste,
+  // the gun fires twice as often
+  RapidFire,
+}
+
+impl PowerUpKind {
+  fn color(&self) -> Color {
+    match self {
+      PowerUpKind::Haste => Color::rgb(1.0, 0.9, 0.2),
+      PowerUpKind::RapidFire => Color::rgb(0.9, 0.3, 0.9),
+    }
+  }
+}
+
+#[derive(Clone, Copy, Debug)]
+pub enum Loot {
+  Nothing,
+  Money(i32),
+  PowerUp(PowerUpKind),
+}
+
+// a prop as placed by the level data
+#[derive(Clone, Copy, Debug)]
+pub struct PropPlacement {
+  pub kind: PropKind,
+  pub position: Vec2,
+  pub loot: Loot,
+}
+
+impl PropPlacement {
+  pub fn new(kind: PropKind, position: Vec2) -> Self {
+    PropPlacement {
+      kind,
+      position,
+      loot: Loot::Nothing,
+    }
+  }
+
+  pub fn with_loot(mut self, loot: Loot) -> Self {
+    self.loot = loot;
+    self
+  }
+}
+
+// something breakable, player weapons hit it like an enemy
+#[derive(Component, Debug)]
+pub struct Prop {
+  pub loot: Loot,
+}
+
+#[derive(Component, Debug)]
+pub struct PowerUpPickup(pub PowerUpKind);
+
+// power-ups a player picked up and how long they still last
+#[derive(Component, Default, Debug)]
+pub struct PowerUps(Vec<(PowerUpKind, Timer)>);
+
+impl PowerUps {
+  const DURATION: f32 = 10.0;
+
+  pub fn has(&self, kind: PowerUpKind) -> bool {
+    self.0.iter().any(|(active, _)| *active == kind)
+  }
+
+  // picking up one that is already active starts it over
+  fn add(&mut self, kind: PowerUpKind) {
+    self.0.retain(|(active, _)| *active != kind);
+    self
+      .0
+      .push((kind, Timer::from_seconds(Self::DURATION, TimerMode::Once)));
+  }
+
+  pub fn speed_multiplier(&self) -> f32 {
+    if self.has(PowerUpKind::Haste) {
+      1.5
+    } else {
+      1.0
+    }
+  }
+
+  pub fn fire_rate(&self) -> f32 {
+    if self.has(PowerUpKind::RapidFire) {
+      2.0
+    } else {
+      1.0
+    }
+  }
+}
+
+pub fn create_props(
+  commands: &mut Commands,
+  textures: &TextureAtlasHandles,
+  placements: &[PropPlacement],
+) {
+  for placement in placements {
+    commands.spawn((
+      OnGameScreen,
+      Prop {
+        loot: placement.loot,
+      },
+      Health::new(placement.kind.health()),
+      SpriteSheetBundle {
+        texture_atlas: textures.props_atlas_handle.clone(),
+        sprite: TextureAtlasSprite::new(placement.kind.sprite_index()),
+        transform: Transform::from_translation(placement.position.extend(0.05)),
+        ..default()
+      },
+      placement.kind.collider(),
+      RigidBody::Fixed,
+      // their own group, so enemies path and steer around them instead of treating them as allies
+      CollisionGroups::new(Group::GROUP_10, Group::ALL),
+    ));
+  }
+}
+
+#[allow(clippy::too_many_arguments)]
+pub fn handle_prop_destroyed(
+  mut commands: Commands,
+  mut despawn_events: EventReader<DespawnEvent>,
+  props: Query<(&Prop, &Transform)>,
+  asset_server: Res<AssetServer>,
+  mut pools: ResMut<EntityPools>,
+  mut pooled: Query<&mut Pooled>,
+  mut rng: ResMut<GameRng>,
+  mut navigation: ResMut<Navigation>,
+) {
+  // loot only drops once, however often the prop was destroyed this step
+  let mut destroyed = HashSet::new();
+
+  for event in despawn_events.iter() {
+    if !destroyed.insert(event.entity) {
+      continue;
+    }
+
+    let (prop, transform) = match props.get(event.entity) {
+      Ok(prop) => prop,
+      Err(_) => continue,
+    };
+    // enemies can walk where it stood
+    navigation.invalidate();
+
+    match prop.loot {
+      Loot::Nothing => (),
+      Loot::Money(amount) => drop_coins(
+        &mut commands,
+        &asset_server,
+        &mut pools,
+        &mut pooled,
+        &mut *rng,
+        transform.translation,
+        amount,
+      ),
+      Loot::PowerUp(kind) => spawn_power_up(&mut commands, kind, transform.translation),
+    }
+  }
+}
+
+fn spawn_power_up(commands: &mut Commands, kind: PowerUpKind, position: Vec3) {
+  let shape = shapes::Circle {
+    radius: 8.0,
+    center: Vec2::ZERO,
+  };
+
+  commands.spawn((
+    OnGameScreen,
+    PowerUpPickup(kind),
+    GeometryBuilder::build_as(
+      &shape,
+      DrawMode::Outlined {
+        fill_mode: FillMode::color(kind.color()),
+        outline_mode: StrokeMode::new(Color::WHITE, 2.0),
+      },
+      Transform::from_translation(position.truncate().extend(0.08)),
+    ),
+    ExpirationTimer(Timer::from_seconds(15.0, TimerMode::Once)),
+    Collider::ball(10.0),
+    Sensor,
+    CollidingEntities::default(),
+    RigidBody::Fixed,
+    // only players pick things up
+    CollisionGroups::new(Group::GROUP_9, Group::GROUP_1),
+  ));
+}
+
+pub fn collect_power_ups(
+  pickups: Query<(Entity, &PowerUpPickup, &CollidingEntities)>,
+  mut players: Query<&mut PowerUps, With<Player>>,
+  mut despawn_events: EventWriter<DespawnEvent>,
+) {
+  for (entity, pickup, colliding) in pickups.iter() {
+    let player = colliding.iter().find(|entity| players.contains(*entity));
+    if let Some(mut power_ups) = player.and_then(|player| players.get_mut(player).ok()) {
+      power_ups.add(pickup.0);
+      despawn_events.send(DespawnEvent { entity });
+    }
+  }
+}
+
+pub fn update_power_ups(mut players: Query<&mut PowerUps>, time: Res<GameTime>) {
+  for mut power_ups in players.iter_mut() {
+    for (_, timer) in power_ups.0.iter_mut() {
+      timer.tick(time.delta());
+    }
+    power_ups.0.retain(|(_, timer)| !timer.finished());
+  }
+}
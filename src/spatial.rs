@@ -0,0 +1,128 @@
+use bevy::prelude::*;
+use bevy::utils::HashMap;
+
+// uniform grid of entity positions, cheap to rebuild every frame and to ask for nearby entities
+pub struct SpatialGrid {
+  cell_size: f32,
+  cells: HashMap<IVec2, Vec<(Entity, Vec2)>>,
+  // bounds of the occupied cells, nearest searches never look further than this
+  min_cell: IVec2,
+  max_cell: IVec2,
+}
+
+impl Default for SpatialGrid {
+  fn default() -> Self {
+    SpatialGrid::new(64.0)
+  }
+}
+
+impl SpatialGrid {
+  pub fn new(cell_size: f32) -> Self {
+    SpatialGrid {
+      cell_size,
+      cells: HashMap::default(),
+      min_cell: IVec2::splat(i32::MAX),
+      max_cell: IVec2::splat(i32::MIN),
+    }
+  }
+
+  fn cell(&self, position: Vec2) -> IVec2 {
+    (position / self.cell_size).floor().as_ivec2()
+  }
+
+  // keeps the allocated cells around so rebuilding doesn't allocate every frame
+  pub fn clear(&mut self) {
+    for entities in self.cells.values_mut() {
+      entities.clear();
+    }
+    self.min_cell = IVec2::splat(i32::MAX);
+    self.max_cell = IVec2::splat(i32::MIN);
+  }
+
+  pub fn insert(&mut self, entity: Entity, position: Vec2) {
+    let cell = self.cell(position);
+    self.min_cell = self.min_cell.min(cell);
+    self.max_cell = self.max_cell.max(cell);
+    self.cells.entry(cell).or_default().push((entity, position));
+  }
+
+  pub fn is_empty(&self) -> bool {
+    self.min_cell.x > self.max_cell.x
+  }
+
+  // every entity within `radius` of `center`
+  pub fn query_radius(
+    &self,
+    center: Vec2,
+    radius: f32,
+  ) -> impl Iterator<Item = (Entity, Vec2)> + '_ {
+    let min = self.cell(center - radius).max(self.min_cell);
+    let max = self.cell(center + radius).min(self.max_cell);
+
+    (min.y..=max.y)
+      .flat_map(move |y| (min.x..=max.x).map(move |x| IVec2::new(x, y)))
+      .filter_map(|cell| self.cells.get(&cell))
+      .flatten()
+      .copied()
+      .filter(move |(_, position)| position.distance_squared(center) < radius * radius)
+  }
+
+  pub fn nearest(&self, center: Vec2) -> Option<(Entity, Vec2)> {
+    if self.is_empty() {
+      return None;
+    }
+
+    let origin = self.cell(center);
+    // no occupied cell is further away than this many rings
+    let max_ring = (origin - self.min_cell)
+      .abs()
+      .max((self.max_cell - origin).abs())
+      .max_element();
+
+    let mut best: Option<(Entity, Vec2, f32)> = None;
+
+    for ring in 0..=max_ring {
+      // anything in this ring or further out is at least this far away
+      if let Some((_, _, distance)) = best {
+        let ring_distance = (ring - 1).max(0) as f32 * self.cell_size;
+        if distance <= ring_distance * ring_distance {
+          break;
+        }
+      }
+
+      for cell in ring_cells(origin, ring) {
+        if let Some(entities) = self.cells.get(&cell) {
+          for (entity, position) in entities {
+            let distance = position.distance_squared(center);
+            if best.map_or(true, |(_, _, best_distance)| distance < best_distance) {
+              best = Some((*entity, *position, distance));
+            }
+          }
+        }
+      }
+    }
+
+    best.map(|(entity, position, _)| (entity, position))
+  }
+}
+
+// cells exactly `ring` steps away from `origin`, the outline of a square
+fn ring_cells(origin: IVec2, ring: i32) -> impl Iterator<Item = IVec2> {
+  (-ring..=ring).flat_map(move |y| {
+    let step = if y.abs() == ring {
+      1
+    } else {
+      (2 * ring).max(1) as usize
+    };
+    (-ring..=ring)
+      .step_by(step)
+      .map(move |x| origin + IVec2::new(x, y))
+  })
+}
+
+// rebuilt after every physics step, shared by targeting, weapons and enemy ai
+#[derive(Resource, Default)]
+pub struct SpatialIndex {
+  pub enemies: SpatialGrid,
+  pub players: SpatialGrid,
+}
@@ -0,0 +1,61 @@
+use crate::components::DamageReduction;
+use crate::enemy::{Enemy, EnemyKind};
+use crate::fixed::GameTime;
+use crate::{DamageEvent, DamageSource, GameData, GameState};
+use bevy::prelude::*;
+use bevy::utils::HashMap;
+
+#[derive(Resource, Default)]
+pub struct RunStats {
+  pub level: Option<GameState>,
+  pub won: bool,
+  pub time: f32,
+  pub kills: HashMap<EnemyKind, usize>,
+  pub damage: HashMap<DamageSource, f32>,
+  pub starting_money: i32,
+  pub money_earned: i32,
+  pub unlock: Option<usize>,
+  pub waves_survived: Option<usize>,
+  pub best_waves: usize,
+}
+
+impl RunStats {
+  pub fn record_kill(&mut self, kind: EnemyKind) {
+    *self.kills.entry(kind).or_insert(0) += 1;
+  }
+
+  // called by the level end conditions before moving to the results screen
+  pub fn finish(&mut self, won: bool, data: &GameData) {
+    self.won = won;
+    self.money_earned = data.money - self.starting_money;
+  }
+}
+
+pub fn reset_run_stats(
+  mut stats: ResMut<RunStats>,
+  game_state: Res<State<GameState>>,
+  data: Res<GameData>,
+) {
+  *stats = RunStats {
+    level: Some(game_state.current().clone()),
+    starting_money: data.money,
+    ..default()
+  };
+}
+
+pub fn track_run_time(mut stats: ResMut<RunStats>, time: Res<GameTime>) {
+  stats.time += time.delta_seconds();
+}
+
+pub fn track_damage(
+  mut stats: ResMut<RunStats>,
+  mut damage_events: EventReader<DamageEvent>,
+  enemies: Query<&DamageReduction, With<Enemy>>,
+) {
+  for damage_event in damage_events.iter() {
+    if let Ok(reduction) = enemies.get(damage_event.entity) {
+      *stats.damage.entry(damage_event.source).or_insert(0.0) +=
+        reduction.apply(damage_event.damage);
+    }
+  }
+}
@@ -1,22 +1,33 @@
 use crate::components::*;
 use crate::enemy::*;
+use crate::fixed::GameTime;
 use crate::player::Player;
+use crate::pool::{EntityPools, Pooled};
+use crate::props::Prop;
+use crate::spatial::SpatialIndex;
 use crate::weapons::gun::Projectile;
 use bevy::prelude::*;
+use bevy::utils::HashSet;
 use bevy_pkv::PkvStore;
 use bevy_rapier2d::prelude::CollisionEvent::Started;
 use bevy_rapier2d::prelude::*;
 
-use crate::{DamageEvent, DespawnEvent, GameData, TextureAtlasHandles};
+use crate::{
+  DamageEvent, DamageSource, DespawnEvent, GameData, Knockback, Settings, TextureAtlasHandles,
+};
 
-pub fn save_game(mut data: ResMut<GameData>, mut pkv: ResMut<PkvStore>) {
+pub fn write_save(data: &mut GameData, pkv: &mut PkvStore) {
   data.new_game = false;
-  match pkv.set("game_save", data.as_ref()) {
+  match pkv.set("game_save", &*data) {
     Ok(_) => println!("Game quick saved"),
     Err(e) => eprintln!("Game quick save failed: {}", e),
   }
 }
 
+pub fn save_game(mut data: ResMut<GameData>, mut pkv: ResMut<PkvStore>) {
+  write_save(&mut data, &mut pkv);
+}
+
 pub fn load_game(mut data: ResMut<GameData>, pkv: ResMut<PkvStore>) {
   match pkv.get::<GameData>("game_save") {
     Ok(save) => {
@@ -27,6 +38,20 @@ pub fn load_game(mut data: ResMut<GameData>, pkv: ResMut<PkvStore>) {
   }
 }
 
+pub fn save_settings(settings: &Settings, pkv: &mut PkvStore) {
+  match pkv.set("settings", settings) {
+    Ok(_) => println!("Settings saved"),
+    Err(e) => eprintln!("Settings save failed: {}", e),
+  }
+}
+
+pub fn load_settings(mut settings: ResMut<Settings>, pkv: ResMut<PkvStore>) {
+  match pkv.get::<Settings>("settings") {
+    Ok(saved) => *settings = saved,
+    Err(e) => eprintln!("Settings load failed: {}", e),
+  }
+}
+
 pub fn initialize_texture_atlas(
   mut commands: Commands,
   asset_server: Res<AssetServer>,
@@ -70,48 +95,94 @@ pub fn initialize_texture_atlas(
   );
   let texture_atlas_handle_enemy3 = texture_atlases.add(texture_atlas_enemy3);
 
+  let texture_handle_props = asset_server.load("props_64x32.png");
+  let texture_atlas_props = TextureAtlas::from_grid(
+    texture_handle_props,
+    Vec2::new(32.0, 32.0),
+    2,
+    1,
+    None,
+    None,
+  );
+  let texture_atlas_handle_props = texture_atlases.add(texture_atlas_props);
+
   commands.insert_resource(TextureAtlasHandles {
     atlas_handle: texture_atlas_handle_enemy,
     elite_atlas_handle: texture_atlas_handle_enemy2,
     boss_atlas_handle: texture_atlas_handle_enemy3,
     player_atlas_handle: texture_atlas_handle,
+    props_atlas_handle: texture_atlas_handle_props,
   });
 }
 
+pub fn rebuild_spatial_index(
+  mut index: ResMut<SpatialIndex>,
+  enemies: Query<(Entity, &Transform), With<Enemy>>,
+  players: Query<(Entity, &Transform), With<Player>>,
+) {
+  index.enemies.clear();
+  for (entity, transform) in enemies.iter() {
+    index
+      .enemies
+      .insert(entity, transform.translation.truncate());
+  }
+
+  index.players.clear();
+  for (entity, transform) in players.iter() {
+    index
+      .players
+      .insert(entity, transform.translation.truncate());
+  }
+}
+
 pub fn handle_despawn_entity(
   mut commands: Commands,
   mut despawn_events: EventReader<DespawnEvent>,
-  enemy_query: Query<(&Enemy, Option<&Explode>)>,
-  mut state: ResMut<GameData>,
+  mut pooled: Query<&mut Pooled>,
+  mut pools: ResMut<EntityPools>,
 ) {
+  // a projectile can hit two enemies in the same step
+  let mut despawned = HashSet::new();
+
   for event in despawn_events.iter() {
-    match enemy_query.get(event.entity) {
-      Ok((enemy, explosive)) => {
-        if explosive.is_none() {
-          state.money += enemy.reward;
-          match commands.get_entity(event.entity) {
-            Some(cmd) => cmd.despawn_recursive(),
-            None => (),
-          }
-        }
-      }
-      Err(_) => match commands.get_entity(event.entity) {
-        Some(cmd) => cmd.despawn_recursive(),
-        None => (),
-      },
+    if !despawned.insert(event.entity) {
+      continue;
+    }
+
+    // projectiles and effects go back to their pool
+    if let Ok(mut pooled) = pooled.get_mut(event.entity) {
+      pools.release(&mut commands, event.entity, &mut pooled);
+      continue;
+    }
+    match commands.get_entity(event.entity) {
+      Some(cmd) => cmd.despawn_recursive(),
+      None => (),
     }
   }
 }
 
 pub fn handle_damage_event(
   mut damage_events: EventReader<DamageEvent>,
-  mut entities_with_health: Query<(&mut Health, &mut TextureAtlasSprite)>,
-  time: Res<Time>,
+  mut entities_with_health: Query<(
+    &mut Health,
+    &mut TextureAtlasSprite,
+    Option<&DamageReduction>,
+  )>,
+  time: Res<GameTime>,
   mut despawn_events: EventWriter<DespawnEvent>,
 ) {
   for damage_event in damage_events.iter() {
-    if let Ok((mut health, mut sprite)) = entities_with_health.get_mut(damage_event.entity) {
-      health.current_health -= damage_event.damage;
+    if let Ok((mut health, mut sprite, reduction)) =
+      entities_with_health.get_mut(damage_event.entity)
+    {
+      // already dead and about to be removed, more damage would kill it again
+      if health.current_health <= 0.0 {
+        continue;
+      }
+
+      health.current_health -= reduction.map_or(damage_event.damage, |reduction| {
+        reduction.apply(damage_event.damage)
+      });
 
       if health.current_health <= 0.0 {
         despawn_events.send(DespawnEvent {
@@ -120,14 +191,17 @@ pub fn handle_damage_event(
       } else {
         sprite.color.set_r(200.0);
         sprite.color.set_g(200.0);
-        sprite.color.set_b(200.0);
+        // crits flash yellow instead of white
+        sprite
+          .color
+          .set_b(if damage_event.crit { 0.0 } else { 200.0 });
         health.dmg_timer.reset();
         health.dmg_timer.unpause();
       }
     }
   }
 
-  for (mut health, mut sprite) in entities_with_health.iter_mut() {
+  for (mut health, mut sprite, _) in entities_with_health.iter_mut() {
     if health.dmg_timer.tick(time.delta()).just_finished() {
       sprite.color.set_r(1.0);
       sprite.color.set_g(1.0);
@@ -136,31 +210,55 @@ pub fn handle_damage_event(
   }
 }
 
-pub fn deal_red_zone_dmg(
-  mut damage_event: EventWriter<DamageEvent>,
-  killzone_query: Query<&CollidingEntities, With<Killzone>>,
-  entities_with_health: Query<Entity, With<Health>>,
-  time: Res<Time>,
+pub fn handle_knockback(
+  mut damage_events: EventReader<DamageEvent>,
+  mut enemies: Query<
+    (
+      &Transform,
+      &ReadMassProperties,
+      &mut ExternalImpulse,
+      &mut Stagger,
+    ),
+    With<Enemy>,
+  >,
+  time: Res<GameTime>,
 ) {
-  for killzone_collision in killzone_query.iter() {
-    for entity in killzone_collision.iter() {
-      if let Ok(health_entity) = entities_with_health.get(entity) {
-        damage_event.send(DamageEvent {
-          entity: health_entity.clone(),
-          damage: 20.0 * time.delta_seconds(),
-        });
+  for damage_event in damage_events.iter() {
+    if let Some(knockback) = &damage_event.knockback {
+      if let Ok((transform, mass, mut impulse, mut stagger)) = enemies.get_mut(damage_event.entity)
+      {
+        let strength = knockback.strength * (1.0 - stagger.resistance);
+        if strength <= 0.0 {
+          continue;
+        }
+
+        let direction = (transform.translation - knockback.origin)
+          .truncate()
+          .normalize_or_zero();
+        impulse.impulse = direction * strength * mass.0.mass;
+
+        stagger.timer.reset();
+        stagger.timer.unpause();
       }
     }
   }
+
+  for (_, _, _, mut stagger) in enemies.iter_mut() {
+    stagger.timer.tick(time.delta());
+  }
 }
 
+#[allow(clippy::too_many_arguments)]
 pub fn handle_collision(
-  projectiles: Query<&Projectile>,
-  mut enemies: Query<(&mut Health, &mut TextureAtlasSprite), With<Enemy>>,
+  projectiles: Query<(&Projectile, &Transform)>,
+  enemy_projectiles: Query<&EnemyProjectile>,
+  enemies: Query<(), With<Enemy>>,
   mut collision_events: EventReader<CollisionEvent>,
   mut damage_event: EventWriter<DamageEvent>,
   mut despawn_event: EventWriter<DespawnEvent>,
   player: Query<Entity, With<Player>>,
+  obstacles: Query<(), With<Obstacle>>,
+  props: Query<(), With<Prop>>,
 ) {
   for collision in collision_events.iter() {
     match collision {
@@ -172,17 +270,45 @@ pub fn handle_collision(
               damage_event.send(DamageEvent {
                 entity: player,
                 damage: 10000.0,
+                crit: false,
+                knockback: None,
+                source: DamageSource::Contact,
               });
             }
           }
 
-          if let Ok(data) = projectiles.get(*entity1) {
-            let damage = data.damage;
-            if let Ok(_) = enemies.get_mut(*entity2) {
+          if let Ok(projectile) = enemy_projectiles.get(*entity1) {
+            if let Ok(player) = player.get(*entity2) {
+              despawn_event.send(DespawnEvent { entity: *entity1 });
+              damage_event.send(DamageEvent {
+                entity: player,
+                damage: projectile.damage,
+                crit: false,
+                knockback: None,
+                source: DamageSource::EnemyProjectile,
+              });
+            }
+          }
+
+          // shots of either side stop at obstacles
+          if obstacles.get(*entity2).is_ok()
+            && (projectiles.get(*entity1).is_ok() || enemy_projectiles.get(*entity1).is_ok())
+          {
+            despawn_event.send(DespawnEvent { entity: *entity1 });
+          }
+
+          if let Ok((data, transform)) = projectiles.get(*entity1) {
+            if enemies.get(*entity2).is_ok() || props.get(*entity2).is_ok() {
               despawn_event.send(DespawnEvent { entity: *entity1 });
               damage_event.send(DamageEvent {
                 entity: entity2.clone(),
-                damage,
+                damage: data.damage,
+                crit: data.crit,
+                knockback: Some(Knockback {
+                  origin: transform.translation,
+                  strength: data.knockback,
+                }),
+                source: DamageSource::Gun,
               });
             }
           }
@@ -212,7 +338,7 @@ pub fn animate_sprite(
 }
 
 pub fn clean_up_expired(
-  time: Res<Time>,
+  time: Res<GameTime>,
   mut query: Query<(Entity, &mut ExpirationTimer)>,
   mut despawn_events: EventWriter<DespawnEvent>,
 ) {
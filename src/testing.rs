@@ -0,0 +1,63 @@
+// a level without window, renderer or input, driven by hand from tests
+use crate::fixed::{self, FixedTimestepPlugin, FixedUpdateStage, GameRng};
+use crate::{damage_text, levels, map, navigation, pool, spatial, stats, waves};
+use crate::{GameData, GameState, Settings, TextureAtlasHandles};
+use bevy::ecs::schedule::IntoSystemDescriptor;
+use bevy::prelude::*;
+use bevy::time::{FixedTimesteps, TimePlugin};
+use bevy_rapier2d::prelude::*;
+use std::time::Duration;
+
+// runs `setup` once and enters the level, no time has passed yet so nothing is simulated
+pub fn headless_app<Params>(seed: u64, setup: impl IntoSystemDescriptor<Params>) -> App {
+  let mut app = App::new();
+  app
+    // time only moves when a test says so
+    .add_plugins(MinimalPlugins.build().disable::<TimePlugin>())
+    .add_plugin(TransformPlugin)
+    .add_plugin(HierarchyPlugin)
+    .add_plugin(AssetPlugin::default())
+    .init_resource::<Time>()
+    .init_resource::<FixedTimesteps>()
+    .add_plugin(
+      RapierPhysicsPlugin::<NoUserData>::pixels_per_meter(100.0).with_default_system_setup(false),
+    )
+    .add_plugin(FixedTimestepPlugin)
+    .insert_resource(GameRng::new(seed))
+    .init_resource::<GameData>()
+    .init_resource::<Settings>()
+    .init_resource::<Windows>()
+    .init_resource::<TextureAtlasHandles>()
+    .init_resource::<stats::RunStats>()
+    .init_resource::<waves::LevelWaves>()
+    .init_resource::<damage_text::DamageTextAccumulator>()
+    .init_resource::<spatial::SpatialIndex>()
+    .init_resource::<pool::EntityPools>()
+    .init_resource::<navigation::Navigation>()
+    .init_resource::<map::Arena>()
+    .add_state(GameState::Level1)
+    .add_startup_system(setup);
+
+  fixed::add_gameplay_systems(&mut app, levels::gameplay_systems(GameState::Level1));
+  app.update();
+  app
+}
+
+// a frame that took `delta`, it runs as many steps as fit into the time passed so far
+pub fn run_frame(app: &mut App, delta: Duration) {
+  let mut time = app.world.resource_mut::<Time>();
+  let now = time.last_update().unwrap_or_else(|| time.startup()) + delta;
+  time.update_with_instant(now);
+  app.update();
+}
+
+// exactly one step of physics and gameplay, without going through a frame
+pub fn step(app: &mut App) {
+  let world = &mut app.world;
+  app
+    .schedule
+    .stage(FixedUpdateStage, |schedule: &mut Schedule| {
+      schedule.run_once(world);
+      schedule
+    });
+}
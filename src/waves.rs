@@ -0,0 +1,215 @@
+use crate::components::*;
+use crate::enemy::*;
+use crate::fixed::GameTime;
+use crate::hud;
+use bevy::prelude::*;
+
+const BANNER_COLOR: Color = Color::rgb(0.9, 0.9, 0.9);
+
+pub struct SpawnerGroup {
+  pub position: Vec3,
+  pub enemy_type: EnemySpawnerType,
+  pub count: usize,
+  pub interval: f32,
+  pub initial_delay: f32,
+  pub scaling: EnemyScaling,
+  pub pattern: SpawnPattern,
+}
+
+impl SpawnerGroup {
+  pub fn new(position: Vec3, enemy_type: EnemySpawnerType, count: usize, interval: f32) -> Self {
+    SpawnerGroup {
+      position,
+      enemy_type,
+      count,
+      interval,
+      initial_delay: 0.0,
+      scaling: EnemyScaling::default(),
+      pattern: SpawnPattern::Point,
+    }
+  }
+
+  pub fn with_delay(mut self, initial_delay: f32) -> Self {
+    self.initial_delay = initial_delay;
+    self
+  }
+
+  pub fn with_scaling(mut self, scaling: EnemyScaling) -> Self {
+    self.scaling = scaling;
+    self
+  }
+
+  // `count` is the total amount of enemies, the pattern decides how many appear per interval
+  pub fn with_pattern(mut self, pattern: SpawnPattern) -> Self {
+    self.pattern = pattern;
+    self
+  }
+}
+
+pub enum WaveTrigger {
+  // the next wave starts once every enemy is dead and every spawner is done
+  Cleared,
+  // the next wave starts after the given seconds, even if enemies are left
+  Timed(f32),
+}
+
+pub struct Wave {
+  pub name: String,
+  pub groups: Vec<SpawnerGroup>,
+  pub trigger: WaveTrigger,
+  pub intermission: Option<f32>,
+}
+
+#[derive(PartialEq, Eq, Debug)]
+pub enum WavePhase {
+  Waiting,
+  Running,
+  Finished,
+}
+
+#[derive(Resource)]
+pub struct LevelWaves {
+  pub waves: Vec<Wave>,
+  pub started: usize,
+  pub completed: usize,
+  pub phase: WavePhase,
+  pub timer: Timer,
+  // endless levels keep queueing waves, so there is no known total
+  pub endless: bool,
+}
+
+impl Default for LevelWaves {
+  fn default() -> Self {
+    LevelWaves::new(Vec::new())
+  }
+}
+
+impl LevelWaves {
+  pub fn new(waves: Vec<Wave>) -> Self {
+    LevelWaves {
+      waves,
+      started: 0,
+      completed: 0,
+      phase: WavePhase::Waiting,
+      timer: Timer::from_seconds(2.0, TimerMode::Once),
+      endless: false,
+    }
+  }
+
+  pub fn endless() -> Self {
+    LevelWaves {
+      endless: true,
+      ..LevelWaves::new(Vec::new())
+    }
+  }
+
+  pub fn is_finished(&self) -> bool {
+    self.phase == WavePhase::Finished
+  }
+}
+
+fn spawn_group(commands: &mut Commands, asset_server: &Res<AssetServer>, group: &SpawnerGroup) {
+  commands.spawn((
+    OnGameScreen,
+    SpriteBundle {
+      texture: asset_server.load("cave.png"),
+      transform: Transform::from_translation(group.position),
+      // rings and edge spawns don't come out of the cave
+      visibility: Visibility {
+        is_visible: matches!(
+          group.pattern,
+          SpawnPattern::Point | SpawnPattern::Burst { .. }
+        ),
+      },
+      ..default()
+    },
+    EnemySpawner {
+      timer: Timer::from_seconds(group.interval, TimerMode::Repeating),
+      initial_delay: Timer::from_seconds(group.initial_delay, TimerMode::Once),
+      spawn_count: 0,
+      spawn_limit: group.count,
+      enemy_type: group.enemy_type.clone(),
+      pattern: group.pattern,
+    },
+    group.scaling,
+  ));
+}
+
+pub fn run_waves(
+  mut commands: Commands,
+  asset_server: Res<AssetServer>,
+  mut waves: ResMut<LevelWaves>,
+  spawners: Query<(Entity, &EnemySpawner)>,
+  enemies: Query<&Enemy>,
+  time: Res<GameTime>,
+) {
+  match waves.phase {
+    WavePhase::Waiting => {
+      if !waves.timer.tick(time.delta()).finished() {
+        return;
+      }
+
+      if waves.started >= waves.waves.len() {
+        // endless levels may still be queueing their next wave
+        if !waves.endless {
+          waves.phase = WavePhase::Finished;
+        }
+        return;
+      }
+
+      // spawners left over from earlier waves are removed once they are done
+      for (entity, spawner) in spawners.iter() {
+        if spawner.spawn_count >= spawner.spawn_limit {
+          commands.entity(entity).despawn_recursive();
+        }
+      }
+
+      let index = waves.started;
+      let wave = &waves.waves[index];
+      for group in wave.groups.iter() {
+        spawn_group(&mut commands, &asset_server, group);
+      }
+
+      hud::spawn_banner(
+        &mut commands,
+        asset_server.load("font.ttf"),
+        wave.name.clone(),
+        BANNER_COLOR,
+        JustifyContent::FlexStart,
+      );
+
+      let duration = match wave.trigger {
+        WaveTrigger::Cleared => 0.0,
+        WaveTrigger::Timed(seconds) => seconds,
+      };
+      waves.timer = Timer::from_seconds(duration, TimerMode::Once);
+      waves.started += 1;
+      waves.phase = WavePhase::Running;
+    }
+    WavePhase::Running => {
+      let (timed, intermission) = {
+        let wave = &waves.waves[waves.started - 1];
+        (
+          matches!(wave.trigger, WaveTrigger::Timed(_)),
+          wave.intermission.unwrap_or(0.0),
+        )
+      };
+
+      let triggered = if timed {
+        waves.timer.tick(time.delta()).finished()
+      } else {
+        enemies.is_empty()
+          && spawners
+            .iter()
+            .all(|(_, spawner)| spawner.spawn_count >= spawner.spawn_limit)
+      };
+
+      if triggered {
+        waves.completed += 1;
+        waves.timer = Timer::from_seconds(intermission, TimerMode::Once);
+        waves.phase = WavePhase::Waiting;
+      }
+    }
+    WavePhase::Finished => (),
+  }
+}
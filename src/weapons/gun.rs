@@ -1,6 +1,9 @@
 use crate::components::*;
-use crate::enemy::Enemy;
+use crate::fixed::{GameRng, GameTime, Interpolated};
 use crate::player::Player;
+use crate::pool::{EntityPools, PoolKind, Pooled};
+use crate::props::PowerUps;
+use crate::spatial::SpatialIndex;
 use bevy::prelude::*;
 use bevy_rapier2d::prelude::*;
 use rand::prelude::*;
@@ -8,69 +11,83 @@ use rand::prelude::*;
 #[derive(Component, Debug)]
 pub struct Projectile {
   pub damage: f32,
+  pub crit: bool,
+  pub knockback: f32,
 }
 
 #[derive(Component, Debug)]
 pub struct Gun {
   pub cooldown: Timer,
   pub damage: f32,
+  pub knockback: f32,
 }
 
-fn closest_enemy(transform: &Transform, enemies: &Query<&Transform, With<Enemy>>) -> Option<Vec3> {
-  let mut closest: Option<Vec3> = None;
-
-  for player in enemies.iter() {
-    match closest {
-      Some(p) => {
-        if transform.translation.distance(player.translation) < transform.translation.distance(p) {
-          closest = Some(player.translation);
-        }
-      }
-      None => closest = Some(player.translation),
-    }
-  }
-
-  closest
-}
-
+#[allow(clippy::too_many_arguments)]
 pub fn spawn_projectiles(
-  time: Res<Time>,
+  time: Res<GameTime>,
   mut commands: Commands,
   asset_server: Res<AssetServer>,
-  mut player_query: Query<(Entity, &Player, &Transform, &mut Gun)>,
-  enemy_query: Query<&Transform, With<Enemy>>,
+  mut player_query: Query<(Entity, &Player, &Transform, &mut Gun, &Critical, &PowerUps)>,
+  index: Res<SpatialIndex>,
+  mut pools: ResMut<EntityPools>,
+  mut pooled: Query<&mut Pooled>,
+  mut rng: ResMut<GameRng>,
 ) {
-  let mut rng = thread_rng();
   let random_angle: f32 = rng.gen_range(0.0..std::f32::consts::PI * 2.0);
   let random_direction = Vec3::new(random_angle.cos(), random_angle.sin(), 0.0);
 
-  for (_, _, player_transform, mut gun) in player_query.iter_mut() {
-    let closest_target = closest_enemy(player_transform, &enemy_query).unwrap_or(random_direction);
+  for (_, _, player_transform, mut gun, critical, power_ups) in player_query.iter_mut() {
+    let closest_target = index
+      .enemies
+      .nearest(player_transform.translation.truncate())
+      .map_or(random_direction, |(_, position)| position.extend(0.0));
     let direction = (closest_target - player_transform.translation).normalize_or_zero();
 
-    gun.cooldown.tick(time.delta());
+    gun
+      .cooldown
+      .tick(time.delta().mul_f32(power_ups.fire_rate()));
 
     let mut new_transform = Transform::from_translation(player_transform.translation);
     new_transform.translation += Vec3::new(direction.x * 20.0, direction.y * 20.0, 0.0);
 
     if gun.cooldown.just_finished() {
-      commands.spawn((
-        OnGameScreen,
-        SpriteBundle {
-          texture: asset_server.load("projectile.png"),
-          transform: new_transform,
-          ..default()
+      let (damage, crit) = critical.roll(gun.damage, &mut *rng);
+      let projectile = pools
+        .acquire(PoolKind::Projectile, &mut pooled)
+        .unwrap_or_else(|| {
+          commands
+            .spawn((
+              OnGameScreen,
+              Pooled::new(PoolKind::Projectile),
+              SpriteBundle {
+                texture: asset_server.load("projectile.png"),
+                ..default()
+              },
+              ActiveEvents::COLLISION_EVENTS,
+              Collider::ball(3.),
+            ))
+            .id()
+        });
+
+      commands.entity(projectile).insert((
+        new_transform,
+        Interpolated::new(new_transform.translation),
+        Visibility::VISIBLE,
+        CollisionGroups::new(
+          Group::GROUP_2,
+          Group::GROUP_3.union(Group::GROUP_7).union(Group::GROUP_10),
+        ),
+        Projectile {
+          damage,
+          crit,
+          knockback: gun.knockback,
         },
-        CollisionGroups::new(Group::GROUP_2, Group::GROUP_3),
-        Projectile { damage: gun.damage },
         ExpirationTimer(Timer::from_seconds(5.0, TimerMode::Once)),
-        ActiveEvents::COLLISION_EVENTS,
         RigidBody::Dynamic,
         Velocity {
           linvel: Vec2::new(direction.x, direction.y) * 400.0,
           angvel: 0.0,
         },
-        Collider::ball(3.),
       ));
     }
   }
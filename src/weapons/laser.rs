@@ -1,13 +1,13 @@
 use crate::components::*;
 use crate::enemy::Enemy;
-use crate::player::Player;
-use crate::Action;
-use crate::DamageEvent;
+use crate::fixed::{GameRng, GameTime};
+use crate::player::{AttackBuffer, Player};
+use crate::props::Prop;
+use crate::{DamageEvent, DamageSource};
 use bevy::prelude::*;
 use bevy_prototype_lyon::prelude::FillMode;
 use bevy_prototype_lyon::prelude::*;
 use bevy_rapier2d::prelude::*;
-use leafwing_input_manager::prelude::*;
 use serde::{Deserialize, Serialize};
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -34,23 +34,32 @@ impl LaserGun {
 #[derive(Component, Debug)]
 pub struct Laser {
   pub damage: f32,
+  pub critical: Critical,
 }
 
 pub fn handle_laser_collision(
   rapier_context: Res<RapierContext>,
   laser_entities: Query<(Entity, &Laser)>,
-  mut enemies: Query<(&mut Health, &mut TextureAtlasSprite), With<Enemy>>,
+  // enemies and props
+  targets: Query<(), Or<(With<Enemy>, With<Prop>)>>,
   mut damage_event: EventWriter<DamageEvent>,
-  time: Res<Time>,
+  time: Res<GameTime>,
+  mut rng: ResMut<GameRng>,
 ) {
   for (entity, laser) in laser_entities.iter() {
     for (col1, col2, intersecting) in rapier_context.intersections_with(entity) {
       if intersecting {
         for (entity1, _) in [(col1, col2), (col2, col1)] {
-          if let Ok(_) = enemies.get_mut(entity1) {
+          if targets.get(entity1).is_ok() {
+            let (damage, crit) = laser
+              .critical
+              .roll(laser.damage * time.delta_seconds(), &mut *rng);
             damage_event.send(DamageEvent {
               entity: entity1,
-              damage: laser.damage * time.delta_seconds(),
+              damage,
+              crit,
+              knockback: None,
+              source: DamageSource::Laser,
             })
           }
         }
@@ -60,13 +69,13 @@ pub fn handle_laser_collision(
 }
 
 pub fn spawn_laser(
-  time: Res<Time>,
+  time: Res<GameTime>,
   mut commands: Commands,
   mut laser_gun_query: Query<(&Parent, &mut LaserGun, &mut Visibility)>,
-  player_query: Query<&ActionState<Action>, With<Player>>,
+  mut player_query: Query<(&mut AttackBuffer, &Critical), With<Player>>,
 ) {
   for (parent, mut laser_gun, mut visibility) in laser_gun_query.iter_mut() {
-    if let Ok(action_state) = player_query.get(parent.get()) {
+    if let Ok((mut attack, critical)) = player_query.get_mut(parent.get()) {
       laser_gun.cooldown.tick(time.delta());
 
       if laser_gun.cooldown.just_finished() {
@@ -78,7 +87,7 @@ pub fn spawn_laser(
         1: Vec2::new(0.0, 0.0),
       };
 
-      if action_state.just_pressed(Action::Attack) && laser_gun.cooldown.finished() {
+      if std::mem::take(&mut attack.0) && laser_gun.cooldown.finished() {
         *visibility = Visibility::INVISIBLE;
 
         laser_gun.cooldown.reset();
@@ -86,11 +95,12 @@ pub fn spawn_laser(
           OnGameScreen,
           Laser {
             damage: laser_gun.damage,
+            critical: *critical,
           },
           ExpirationTimer(Timer::from_seconds(1.75, TimerMode::Once)),
           ActiveEvents::COLLISION_EVENTS,
           Sensor,
-          CollisionGroups::new(Group::GROUP_5, Group::GROUP_3),
+          CollisionGroups::new(Group::GROUP_5, Group::GROUP_3.union(Group::GROUP_10)),
           Collider::polyline(vec![Vec2::default(), Vec2::default()], None),
           GeometryBuilder::build_as(
             &shape,
@@ -110,13 +120,23 @@ pub fn update_laser(
   mut commands: Commands,
   mut laser_query: Query<(Entity, &mut Path, &mut Collider), With<Laser>>,
   players_query: Query<&Transform, With<Player>>,
+  rapier_context: Res<RapierContext>,
 ) {
   if players_query.iter().count() >= 2 {
     let mut player_iter = players_query.iter();
     let p1 = player_iter.next().unwrap();
     let p2 = player_iter.next().unwrap();
     let v1 = Vec2::new(p1.translation.x, p1.translation.y);
-    let v2 = Vec2::new(p2.translation.x, p2.translation.y);
+    let mut v2 = Vec2::new(p2.translation.x, p2.translation.y);
+
+    // the beam stops at the first obstacle between the players
+    let obstacles = QueryFilter::new()
+      .exclude_sensors()
+      .groups(CollisionGroups::new(Group::GROUP_5, Group::GROUP_7).into());
+    if let Some((_, toi)) = rapier_context.cast_ray(v1, v2 - v1, 1.0, true, obstacles) {
+      v2 = v1 + (v2 - v1) * toi;
+    }
+
     let shape = shapes::Line {
       0: v1.clone(),
       1: v2.clone(),
@@ -1,10 +1,11 @@
 use crate::components::*;
-use crate::enemy::Enemy;
-use crate::player::Player;
-use crate::Action;
-use crate::DamageEvent;
+use crate::fixed::{GameRng, GameTime};
+use crate::player::{AttackBuffer, Player};
+use crate::pool::{EntityPools, PoolKind, Pooled};
+use crate::props::Prop;
+use crate::spatial::SpatialIndex;
+use crate::{DamageEvent, DamageSource, Knockback};
 use bevy::prelude::*;
-use leafwing_input_manager::prelude::*;
 use serde::{Deserialize, Serialize};
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -12,6 +13,12 @@ pub struct LightningGunConfig {
   pub cooldown: f32,
   pub damage: f32,
   pub size: f32,
+  #[serde(default = "default_lightning_knockback")]
+  pub knockback: f32,
+}
+
+fn default_lightning_knockback() -> f32 {
+  300.0
 }
 
 #[derive(Component, Debug)]
@@ -19,6 +26,7 @@ pub struct LightningGun {
   pub cooldown: Timer,
   pub damage: f32,
   pub size: f32,
+  pub knockback: f32,
 }
 
 impl LightningGun {
@@ -27,18 +35,24 @@ impl LightningGun {
       cooldown: Timer::from_seconds(config.cooldown, TimerMode::Once),
       damage: config.damage,
       size: config.size,
+      knockback: config.knockback,
     }
   }
 }
 
+#[allow(clippy::too_many_arguments)]
 pub fn spawn_lightning(
-  time: Res<Time>,
+  time: Res<GameTime>,
   asset_server: Res<AssetServer>,
   mut commands: Commands,
   mut lightning_gun_query: Query<(&Parent, &mut LightningGun, &mut Visibility)>,
-  player_query: Query<(&ActionState<Action>, &Transform), With<Player>>,
-  enemy_query: Query<(Entity, &Transform), With<Enemy>>,
+  mut player_query: Query<(&mut AttackBuffer, &Transform, &Critical), With<Player>>,
+  index: Res<SpatialIndex>,
+  props: Query<(Entity, &Transform), With<Prop>>,
+  mut pools: ResMut<EntityPools>,
+  mut pooled: Query<&mut Pooled>,
   mut damage_event: EventWriter<DamageEvent>,
+  mut rng: ResMut<GameRng>,
 ) {
   for (parent, mut lightning_gun, mut visibility) in lightning_gun_query.iter_mut() {
     lightning_gun.cooldown.tick(time.delta());
@@ -47,32 +61,56 @@ pub fn spawn_lightning(
       *visibility = Visibility::VISIBLE;
     }
 
-    if let Ok((action_state, player_transform)) = player_query.get(parent.get()) {
-      if action_state.just_pressed(Action::Attack) {
+    if let Ok((mut attack, player_transform, critical)) = player_query.get_mut(parent.get()) {
+      if std::mem::take(&mut attack.0) {
         if lightning_gun.cooldown.finished() {
           *visibility = Visibility::INVISIBLE;
           let scale = lightning_gun.size;
 
-          commands.spawn((
-            OnGameScreen,
-            SpriteBundle {
-              texture: asset_server.load("lightning.png"),
-              transform: player_transform.with_scale(Vec3::new(scale, scale, scale)),
-              ..default()
-            },
+          let flash = pools
+            .acquire(PoolKind::LightningFlash, &mut pooled)
+            .unwrap_or_else(|| {
+              commands
+                .spawn((
+                  OnGameScreen,
+                  Pooled::new(PoolKind::LightningFlash),
+                  SpriteBundle {
+                    texture: asset_server.load("lightning.png"),
+                    ..default()
+                  },
+                ))
+                .id()
+            });
+          commands.entity(flash).insert((
+            player_transform.with_scale(Vec3::new(scale, scale, scale)),
+            Visibility::VISIBLE,
             ExpirationTimer(Timer::from_seconds(0.1, TimerMode::Once)),
           ));
 
-          for (enemy_entity, enemy_transform) in enemy_query.iter() {
-            let distance = player_transform
-              .translation
-              .distance(enemy_transform.translation);
-            if distance < 75.0 * scale / 2.0 {
-              damage_event.send(DamageEvent {
-                entity: enemy_entity,
-                damage: lightning_gun.damage,
-              });
-            }
+          let radius = 75.0 * scale / 2.0;
+          let center = player_transform.translation.truncate();
+          // props are few and don't move, so they are not in the spatial index
+          let props_hit = props
+            .iter()
+            .filter(|(_, transform)| transform.translation.truncate().distance(center) < radius)
+            .map(|(entity, _)| entity);
+          let enemies_hit = index
+            .enemies
+            .query_radius(center, radius)
+            .map(|(entity, _)| entity);
+
+          for target in enemies_hit.chain(props_hit) {
+            let (damage, crit) = critical.roll(lightning_gun.damage, &mut *rng);
+            damage_event.send(DamageEvent {
+              entity: target,
+              damage,
+              crit,
+              knockback: Some(Knockback {
+                origin: player_transform.translation,
+                strength: lightning_gun.knockback,
+              }),
+              source: DamageSource::Lightning,
+            });
           }
           lightning_gun.cooldown.reset();
         }